@@ -0,0 +1,38 @@
+//! Checks an LDtk map without starting the game, exits with an error if the map has mistakes
+//!
+//! `cargo run --bin validate-map [map]`, the map defaults to the one of the game.
+
+use std::{env, fs, process::ExitCode};
+
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use ra_lex_jin::dungeon::{validation::validate, MAP_PATH};
+
+fn main() -> ExitCode {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| format!("assets/{MAP_PATH}"));
+
+    let project: LdtkJson = match fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|error| error.to_string()))
+    {
+        Ok(project) => project,
+        Err(error) => {
+            eprintln!("error: cannot read {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let errors = validate(&project);
+    for error in &errors {
+        eprintln!("error: {error}");
+    }
+
+    if errors.is_empty() {
+        println!("{path} is valid");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("{path} has {} errors", errors.len());
+        ExitCode::FAILURE
+    }
+}
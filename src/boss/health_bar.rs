@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::combat::Health;
+
+use super::*;
+
+const HEALTH_BAR_FONT_SIZE: f32 = 24.;
+const HEALTH_BAR_BACKGROUND_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const HEALTH_BAR_COLOR: Color = Color::rgb(0.75, 0.1, 0.15);
+
+pub(super) struct BossHealthBarPlugin;
+
+impl Plugin for BossHealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_boss_health_bar);
+    }
+}
+
+/// Shown at the bottom of the screen while a boss is engaged
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossHealthBarFill;
+
+fn update_boss_health_bar(
+    mut commands: Commands,
+    definitions: Res<Assets<BossDefinition>>,
+    boss_query: Query<(&Health, &Handle<BossDefinition>), With<BossEngaged>>,
+    health_bar_query: Query<Entity, With<BossHealthBar>>,
+    mut fill_query: Query<&mut Style, With<BossHealthBarFill>>,
+) {
+    let boss = boss_query
+        .iter()
+        .find_map(|(health, handle)| Some((health, definitions.get(handle)?)));
+
+    match (boss, health_bar_query.get_single()) {
+        (Some((health, definition)), Ok(_)) => {
+            let fraction = (health.current as f32 / definition.health as f32).clamp(0., 1.);
+            for mut style in fill_query.iter_mut() {
+                style.width = Val::Percent(fraction * 100.);
+            }
+        }
+        (Some((_, definition)), Err(_)) => spawn_boss_health_bar(&mut commands, &definition.name),
+        (None, Ok(health_bar)) => commands.entity(health_bar).despawn_recursive(),
+        (None, Err(_)) => (),
+    }
+}
+
+fn spawn_boss_health_bar(commands: &mut Commands, name: &str) {
+    commands
+        .spawn((
+            BossHealthBar,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(24.),
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|health_bar| {
+            health_bar.spawn(TextBundle::from_section(
+                name,
+                TextStyle {
+                    font_size: HEALTH_BAR_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            health_bar
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(50.),
+                        height: Val::Px(12.),
+                        ..default()
+                    },
+                    background_color: HEALTH_BAR_BACKGROUND_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|background| {
+                    background.spawn((
+                        BossHealthBarFill,
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: HEALTH_BAR_COLOR.into(),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
@@ -0,0 +1,532 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    character_controller_dynamic::{AccelerationScale, Stunned},
+    combat::{
+        apply_damage, bullet_pattern::Shooter, despawn_dead, hitbox::*,
+        status_effect::StatusEffects, Attack, Health,
+    },
+    dungeon::{
+        collisions::Door,
+        rooms::{CurrentRoom, RoomEntered, RoomExited},
+    },
+    layers::Layer,
+    ldtk_fields::{insert_from_fields, EntityFields, FieldError, FromFields},
+    mob::MOB_CONTACT_DAMAGE_DELAY,
+    player::Player,
+    ron_asset::RonAssetPlugin,
+    run::RunState,
+};
+
+use self::health_bar::*;
+
+mod health_bar;
+
+pub const BOSS_CONTACT_DAMAGE: i32 = 20;
+pub const BOSS_DAMPING: f32 = 8.;
+/// Time for an orbiting boss to catch up with its position on the orbit, in seconds
+const BOSS_ORBIT_CATCH_UP: f32 = 0.25;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RonAssetPlugin::<BossDefinition>::new(&["boss.ron"]),
+            BossHealthBarPlugin,
+        ))
+        .add_event::<BossDefeated>()
+        .register_ldtk_entity::<BossBundle>("Boss")
+        .add_systems(
+            Update,
+            (
+                insert_from_fields::<Boss, BossMarker>,
+                load_boss_definitions,
+                initialize_bosses,
+                advance_boss_phases,
+                engage_bosses,
+                release_bosses,
+                close_boss_room_doors
+                    .after(engage_bosses)
+                    .after(release_bosses),
+                move_bosses,
+                detect_boss_defeat.after(apply_damage).before(despawn_dead),
+                win_run_on_final_boss_defeat.after(detect_boss_defeat),
+            ),
+        );
+    }
+}
+
+/// Health and phases of a boss, loaded from `.boss.ron` files
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct BossDefinition {
+    pub name: String,
+    pub health: i32,
+    /// Phases ordered by decreasing threshold, the first one starts the fight
+    pub phases: Vec<BossPhaseDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPhaseDefinition {
+    /// Fraction of the maximum health at or below which the phase starts
+    pub threshold: f32,
+    /// Bullet patterns fired during the phase, relative to the assets folder
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub movement: BossMovement,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub enum BossMovement {
+    #[default]
+    Still,
+    /// Walk toward the player
+    Chase { speed: f32 },
+    /// Circle around the spawn point, `period` is the time of a full turn in seconds
+    Orbit { radius: f32, period: f32 },
+}
+
+impl BossDefinition {
+    /// Index of the phase matching the remaining health
+    pub fn phase(&self, health: i32) -> usize {
+        let fraction = health as f32 / self.health as f32;
+        self.phases
+            .iter()
+            .rposition(|phase| fraction <= phase.threshold)
+            .unwrap_or(0)
+    }
+}
+
+/// Custom fields of the `Boss` LDtk entity
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Boss {
+    /// Boss definition, relative to the assets folder
+    pub definition: String,
+    /// Defeating the final boss wins the run
+    pub final_boss: bool,
+}
+
+/// Bosses cannot fight without their definition
+impl FromFields for Boss {
+    const REQUIRED: bool = true;
+
+    fn from_fields(fields: &EntityFields) -> Result<Self, FieldError> {
+        Ok(Boss {
+            definition: fields.get("Definition")?,
+            final_boss: fields.get("Final")?,
+        })
+    }
+}
+
+/// Boss placed in LDtk, [`Boss`] is added once its fields are read
+#[derive(Default, Component)]
+pub struct BossMarker;
+
+/// Current phase of a boss whose definition is loaded,
+/// the boss can only be hit once this component is added
+#[derive(Component)]
+pub struct BossPhase {
+    pub index: usize,
+    origin: Vec2,
+}
+
+/// The boss is in the same room as the player
+#[derive(Component)]
+pub struct BossEngaged;
+
+/// Fires one of the patterns of the current phase of its parent boss
+#[derive(Component)]
+struct BossAttack;
+
+/// An event sent when a boss dies
+#[derive(Event)]
+pub struct BossDefeated {
+    pub boss: Entity,
+    pub final_boss: bool,
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct BossBundle {
+    pub boss_marker: BossMarker,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(boss_hitbox)]
+    pub collider: Collider,
+    pub rigid_body: RigidBody,
+    #[with(boss_locked_axes)]
+    pub locked_axes: LockedAxes,
+    #[with(boss_damping)]
+    pub linear_damping: LinearDamping,
+    #[with(boss_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    #[with(boss_contact_hitbox)]
+    pub contact_hitbox: Hitbox,
+    #[with(boss_contact_attack)]
+    pub contact_attack: Attack,
+    pub status_effects: StatusEffects,
+}
+
+fn boss_hitbox(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(entity_instance.width as f32, entity_instance.height as f32)
+}
+
+fn boss_locked_axes(_: &EntityInstance) -> LockedAxes {
+    LockedAxes::ROTATION_LOCKED
+}
+
+fn boss_damping(_: &EntityInstance) -> LinearDamping {
+    LinearDamping(BOSS_DAMPING)
+}
+
+fn boss_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Layer::mob()
+}
+
+fn boss_contact_hitbox(_: &EntityInstance) -> Hitbox {
+    Hitbox::new(Team::Enemy).with_rehit_delay(MOB_CONTACT_DAMAGE_DELAY)
+}
+
+fn boss_contact_attack(_: &EntityInstance) -> Attack {
+    Attack::new(BOSS_CONTACT_DAMAGE)
+}
+
+fn load_boss_definitions(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    boss_query: Query<(Entity, &Boss), Added<Boss>>,
+) {
+    for (entity, boss) in boss_query.iter() {
+        commands
+            .entity(entity)
+            .insert(asset_server.load::<BossDefinition>(&boss.definition));
+    }
+}
+
+/// Gives health to the bosses once their definition is loaded
+#[allow(clippy::type_complexity)]
+fn initialize_bosses(
+    mut commands: Commands,
+    definitions: Res<Assets<BossDefinition>>,
+    boss_query: Query<
+        (Entity, &Handle<BossDefinition>, &Transform),
+        (With<Boss>, Without<BossPhase>),
+    >,
+) {
+    for (entity, handle, transform) in boss_query.iter() {
+        let Some(definition) = definitions.get(handle) else {
+            continue;
+        };
+
+        commands.entity(entity).insert((
+            Health::new(definition.health),
+            Hurtbox::new(Team::Enemy),
+            BossPhase {
+                index: 0,
+                origin: transform.translation.truncate(),
+            },
+        ));
+    }
+}
+
+fn spawn_boss_attacks(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    boss: Entity,
+    definition: &BossDefinition,
+    phase: usize,
+) {
+    let Some(phase) = definition.phases.get(phase) else {
+        return;
+    };
+
+    commands.entity(boss).with_children(|boss| {
+        for pattern in &phase.patterns {
+            boss.spawn((
+                BossAttack,
+                Shooter::new(Team::Enemy, asset_server.load(pattern)),
+                TransformBundle::default(),
+            ));
+        }
+    });
+}
+
+fn despawn_boss_attacks(
+    commands: &mut Commands,
+    children: Option<&Children>,
+    attack_query: &Query<With<BossAttack>>,
+) {
+    for &child in children.into_iter().flatten() {
+        if attack_query.contains(child) {
+            commands.entity(child).despawn_recursive();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn advance_boss_phases(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    definitions: Res<Assets<BossDefinition>>,
+    mut boss_query: Query<
+        (
+            Entity,
+            &Health,
+            &mut BossPhase,
+            &Handle<BossDefinition>,
+            Option<&Children>,
+            Has<BossEngaged>,
+        ),
+        Changed<Health>,
+    >,
+    attack_query: Query<With<BossAttack>>,
+) {
+    for (entity, health, mut phase, handle, children, engaged) in boss_query.iter_mut() {
+        let Some(definition) = definitions.get(handle) else {
+            continue;
+        };
+
+        let index = definition.phase(health.current);
+        // dead bosses are despawned this frame
+        if index <= phase.index || health.is_dead() {
+            continue;
+        }
+        phase.index = index;
+
+        if engaged {
+            despawn_boss_attacks(&mut commands, children, &attack_query);
+            spawn_boss_attacks(&mut commands, &asset_server, entity, definition, index);
+        }
+    }
+}
+
+/// Level entity of an entity spawned on an LDtk entity layer
+fn entity_level(entity: Entity, parent_query: &Query<&Parent>) -> Option<Entity> {
+    let layer = parent_query.get(entity).ok()?.get();
+    Some(parent_query.get(layer).ok()?.get())
+}
+
+/// LDtk IID of the level of an entity spawned on an LDtk entity layer
+fn entity_level_iid<'a>(
+    entity: Entity,
+    parent_query: &Query<&Parent>,
+    level_query: &'a Query<&LevelIid>,
+) -> Option<&'a LevelIid> {
+    level_query.get(entity_level(entity, parent_query)?).ok()
+}
+
+/// Bosses fight once the player enters their room,
+/// or once they are ready if the player is already there
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn engage_bosses(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    definitions: Res<Assets<BossDefinition>>,
+    current_room: Res<CurrentRoom>,
+    mut room_entered_reader: EventReader<RoomEntered>,
+    boss_query: Query<(Entity, Ref<BossPhase>, &Handle<BossDefinition>), Without<BossEngaged>>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+) {
+    let entered = room_entered_reader
+        .read()
+        .last()
+        .map(|event| &event.level_iid);
+
+    for (boss, phase, handle) in boss_query.iter() {
+        let Some(level_iid) = entity_level_iid(boss, &parent_query, &level_query) else {
+            continue;
+        };
+        let entered_room = entered == Some(level_iid);
+        let ready_in_room = phase.is_added() && current_room.level_iid() == Some(level_iid);
+        if !entered_room && !ready_in_room {
+            continue;
+        }
+
+        commands.entity(boss).insert(BossEngaged);
+        if let Some(definition) = definitions.get(handle) {
+            spawn_boss_attacks(&mut commands, &asset_server, boss, definition, phase.index);
+        }
+    }
+}
+
+/// Bosses stop fighting once the player leaves their room
+fn release_bosses(
+    mut commands: Commands,
+    mut room_exited_reader: EventReader<RoomExited>,
+    boss_query: Query<(Entity, Option<&Children>), With<BossEngaged>>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    attack_query: Query<With<BossAttack>>,
+) {
+    for event in room_exited_reader.read() {
+        for (boss, children) in boss_query.iter() {
+            if entity_level_iid(boss, &parent_query, &level_query) == Some(&event.level_iid) {
+                commands.entity(boss).remove::<BossEngaged>();
+                despawn_boss_attacks(&mut commands, children, &attack_query);
+            }
+        }
+    }
+}
+
+/// The doors of a room stay closed while the player fights its boss,
+/// they are only updated when a fight starts or ends and when doors spawn
+fn close_boss_room_doors(
+    mut released_bosses: RemovedComponents<BossEngaged>,
+    engaged_query: Query<(), Added<BossEngaged>>,
+    new_door_query: Query<(), Added<Door>>,
+    boss_query: Query<Entity, With<BossEngaged>>,
+    parent_query: Query<&Parent>,
+    mut door_query: Query<(&Parent, &mut CollisionLayers), With<Door>>,
+) {
+    let released = released_bosses.read().count() > 0;
+    if !released && engaged_query.is_empty() && new_door_query.is_empty() {
+        return;
+    }
+
+    let boss_levels: HashSet<Entity> = boss_query
+        .iter()
+        .filter_map(|boss| entity_level(boss, &parent_query))
+        .collect();
+
+    for (level, mut layers) in door_query.iter_mut() {
+        let target = if boss_levels.contains(&level.get()) {
+            Door::closed()
+        } else {
+            Door::open()
+        };
+
+        if *layers != target {
+            *layers = target;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_bosses(
+    time: Res<Time>,
+    definitions: Res<Assets<BossDefinition>>,
+    mut boss_query: Query<
+        (
+            &mut LinearVelocity,
+            &Transform,
+            &GlobalTransform,
+            &BossPhase,
+            &Handle<BossDefinition>,
+            Option<&AccelerationScale>,
+            Has<Stunned>,
+        ),
+        With<BossEngaged>,
+    >,
+    player_query: Query<&GlobalTransform, With<Player>>,
+) {
+    for (
+        mut linear_velocity,
+        transform,
+        global_transform,
+        phase,
+        handle,
+        acceleration_scale,
+        stunned,
+    ) in boss_query.iter_mut()
+    {
+        let Some(movement) = definitions
+            .get(handle)
+            .and_then(|definition| definition.phases.get(phase.index))
+            .map(|phase| &phase.movement)
+        else {
+            continue;
+        };
+        if stunned {
+            linear_velocity.0 = Vec2::ZERO;
+            continue;
+        }
+
+        let velocity = match *movement {
+            BossMovement::Still => Vec2::ZERO,
+            BossMovement::Chase { speed } => match player_query.get_single() {
+                Ok(player_transform) => {
+                    speed
+                        * (player_transform.translation() - global_transform.translation())
+                            .truncate()
+                            .normalize_or_zero()
+                }
+                Err(_) => Vec2::ZERO,
+            },
+            BossMovement::Orbit { radius, period } => {
+                let angle = time.elapsed_seconds() * TAU / period;
+                // the origin and the transform are both relative to the level
+                let target = phase.origin + radius * Vec2::from_angle(angle);
+                (target - transform.translation.truncate()) / BOSS_ORBIT_CATCH_UP
+            }
+        };
+        linear_velocity.0 = velocity * acceleration_scale.map_or(1., |scale| scale.0);
+    }
+}
+
+fn detect_boss_defeat(
+    boss_query: Query<(Entity, &Boss, &Health), Changed<Health>>,
+    mut boss_defeated_event_writer: EventWriter<BossDefeated>,
+) {
+    for (entity, boss, health) in boss_query.iter() {
+        if health.is_dead() {
+            boss_defeated_event_writer.send(BossDefeated {
+                boss: entity,
+                final_boss: boss.final_boss,
+            });
+        }
+    }
+}
+
+fn win_run_on_final_boss_defeat(
+    mut boss_defeated_event_reader: EventReader<BossDefeated>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    if boss_defeated_event_reader
+        .read()
+        .any(|boss_defeated| boss_defeated.final_boss)
+    {
+        next_run_state.set(RunState::Victory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_bosses_are_valid() {
+        for entry in std::fs::read_dir("assets/bosses").unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            if let Err(error) = ron::from_str::<BossDefinition>(&content) {
+                panic!("{}: {error}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn phases_start_at_their_health_threshold() {
+        let phase = |threshold| BossPhaseDefinition {
+            threshold,
+            patterns: Vec::new(),
+            movement: BossMovement::Still,
+        };
+        let definition = BossDefinition {
+            name: "Test".into(),
+            health: 100,
+            phases: vec![phase(1.), phase(0.5), phase(0.2)],
+        };
+
+        assert_eq!(definition.phase(100), 0);
+        assert_eq!(definition.phase(51), 0);
+        assert_eq!(definition.phase(50), 1);
+        assert_eq!(definition.phase(21), 1);
+        assert_eq!(definition.phase(20), 2);
+        assert_eq!(definition.phase(0), 2);
+    }
+}
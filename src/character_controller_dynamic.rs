@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 use bevy_ecs_ldtk::EntityInstance;
 use bevy_xpbd_2d::{
@@ -5,7 +7,12 @@ use bevy_xpbd_2d::{
     math::{AdjustPrecision, Scalar, Vector2},
 };
 
-use crate::player::{PLAYER_ACCELERATION, PLAYER_DAMPING};
+use crate::{
+    combat::Invulnerable,
+    player::{PLAYER_ACCELERATION, PLAYER_DAMPING},
+    run::RunState,
+    stats::{Stat, Stats},
+};
 
 pub struct CharacterControllerPlugin;
 
@@ -14,11 +21,12 @@ impl Plugin for CharacterControllerPlugin {
         app.add_event::<MovementAction>().add_systems(
             Update,
             (
-                keyboard_input,
-                gamepad_input,
+                // Input is frozen while the game is paused, such as during room transitions
+                (keyboard_input, gamepad_input).run_if(in_state(RunState::Playing)),
                 apply_deferred,
-                movement,
+                tick_dash,
                 apply_movement_damping,
+                movement,
             )
                 .chain(),
         );
@@ -29,6 +37,8 @@ impl Plugin for CharacterControllerPlugin {
 #[derive(Event)]
 pub enum MovementAction {
     Move(Vector2),
+    /// Dash toward the given direction, or along the current velocity if it is zero
+    Dash(Vector2),
 }
 
 /// A marker component indicating that an entity is using a character controller.
@@ -39,10 +49,91 @@ pub struct CharacterController;
 #[derive(Component)]
 pub struct MovementAcceleration(Scalar);
 
+/// Scales the [`MovementAcceleration`] of a character, e.g. while it is slowed down.
+#[derive(Component)]
+pub struct AccelerationScale(pub Scalar);
+
+/// Movement actions are ignored while this component is present.
+#[derive(Component)]
+pub struct Stunned;
+
 /// The damping factor used for slowing down movement.
+///
+/// This is the fraction of the velocity kept after one frame at [`DAMPING_REFERENCE_FPS`],
+/// the damping is applied exponentially so that movement does not depend on the frame rate.
 #[derive(Component)]
 pub struct MovementDampingFactor(Scalar);
 
+/// Frame rate at which [`MovementDampingFactor`] values are expressed.
+pub const DAMPING_REFERENCE_FPS: Scalar = 60.0;
+
+impl MovementDampingFactor {
+    /// Exponential decay rate of the velocity, per second.
+    fn decay_rate(&self) -> Scalar {
+        -self.0.ln() * DAMPING_REFERENCE_FPS
+    }
+
+    /// Fraction of the velocity kept after `delta_time` seconds.
+    fn decay(&self, delta_time: Scalar) -> Scalar {
+        (-self.decay_rate() * delta_time).exp()
+    }
+
+    /// Time during which an acceleration effectively applies over `delta_time` seconds,
+    /// accounting for the damping of the velocity it adds along the way.
+    fn effective_time(&self, delta_time: Scalar) -> Scalar {
+        let decay_rate = self.decay_rate();
+        if decay_rate <= Scalar::EPSILON {
+            delta_time
+        } else {
+            (1.0 - self.decay(delta_time)) / decay_rate
+        }
+    }
+}
+
+/// Allows a character controller to respond to [`MovementAction::Dash`].
+#[derive(Component)]
+pub struct DashAbility {
+    speed: Scalar,
+    duration: Duration,
+    invulnerability: Duration,
+    cooldown: Timer,
+}
+
+impl DashAbility {
+    pub fn new(
+        speed: Scalar,
+        duration: Duration,
+        invulnerability: Duration,
+        cooldown: Duration,
+    ) -> Self {
+        let mut cooldown = Timer::new(cooldown, TimerMode::Once);
+        // Allow dashing right after spawning
+        cooldown.tick(cooldown.duration());
+
+        Self {
+            speed,
+            duration,
+            invulnerability,
+            cooldown,
+        }
+    }
+}
+
+impl Default for DashAbility {
+    fn default() -> Self {
+        Self::new(
+            300.0,
+            Duration::from_millis(150),
+            Duration::from_millis(200),
+            Duration::from_secs(1),
+        )
+    }
+}
+
+/// Present while a character is dashing, movement damping and inputs are ignored.
+#[derive(Component)]
+pub struct Dashing(Timer);
+
 /// A bundle that contains the components needed for a basic
 /// kinematic character controller.
 #[derive(Bundle, Default)]
@@ -120,6 +211,10 @@ fn keyboard_input(
     let vertical = up as i8 - down as i8;
     let direction = Vector2::new(horizontal as Scalar, vertical as Scalar).clamp_length_max(1.0);
 
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        movement_event_writer.send(MovementAction::Dash(direction));
+    }
+
     if direction != Vector2::ZERO {
         movement_event_writer.send(MovementAction::Move(direction));
     }
@@ -130,6 +225,7 @@ fn gamepad_input(
     mut movement_event_writer: EventWriter<MovementAction>,
     gamepads: Res<Gamepads>,
     axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<Input<GamepadButton>>,
 ) {
     for gamepad in gamepads.iter() {
         let axis_lx = GamepadAxis {
@@ -142,39 +238,185 @@ fn gamepad_input(
         };
 
         if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-            movement_event_writer.send(MovementAction::Move(
-                Vector2::new(x as Scalar, y as Scalar).clamp_length_max(1.0),
-            ));
+            let direction = Vector2::new(x as Scalar, y as Scalar).clamp_length_max(1.0);
+
+            if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::East)) {
+                movement_event_writer.send(MovementAction::Dash(direction));
+            }
+
+            movement_event_writer.send(MovementAction::Move(direction));
+        }
+    }
+}
+
+/// Ends dashes and recharges dash cooldowns.
+fn tick_dash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dash_abilities: Query<&mut DashAbility>,
+    mut dashing: Query<(Entity, &mut Dashing)>,
+) {
+    for mut dash_ability in &mut dash_abilities {
+        dash_ability.cooldown.tick(time.delta());
+    }
+
+    for (entity, mut dashing) in &mut dashing {
+        if dashing.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Dashing>();
         }
     }
 }
 
 /// Responds to [`MovementAction`] events and moves character controllers accordingly.
+///
+/// Characters with [`Stats`] use their [`Stat::MoveAcceleration`] instead of their
+/// [`MovementAcceleration`].
+#[allow(clippy::type_complexity)]
 fn movement(
+    mut commands: Commands,
     time: Res<Time>,
     mut movement_event_reader: EventReader<MovementAction>,
-    mut controllers: Query<(&MovementAcceleration, &mut LinearVelocity)>,
+    mut controllers: Query<(
+        Entity,
+        &MovementAcceleration,
+        &MovementDampingFactor,
+        &mut LinearVelocity,
+        Option<&mut DashAbility>,
+        Option<&AccelerationScale>,
+        Option<&Stats>,
+        Has<Dashing>,
+        Has<Stunned>,
+    )>,
 ) {
     // Precision is adjusted so that the example works with
     // both the `f32` and `f64` features. Otherwise you don't need this.
     let delta_time = time.delta_seconds_f64().adjust_precision();
 
     for event in movement_event_reader.read() {
-        for (movement_acceleration, mut linear_velocity) in &mut controllers {
+        for (
+            entity,
+            movement_acceleration,
+            damping_factor,
+            mut linear_velocity,
+            dash_ability,
+            acceleration_scale,
+            stats,
+            is_dashing,
+            is_stunned,
+        ) in &mut controllers
+        {
+            if is_dashing || is_stunned {
+                continue;
+            }
+            let acceleration = stats.map_or(movement_acceleration.0, |stats| {
+                stats.get(Stat::MoveAcceleration)
+            }) * acceleration_scale.map_or(1.0, |scale| scale.0);
+
             match event {
                 MovementAction::Move(direction) => {
-                    linear_velocity.x += direction.x * movement_acceleration.0 * delta_time;
-                    linear_velocity.y -= direction.y * movement_acceleration.0 * delta_time;
+                    // Integrating the acceleration over the damped step keeps the top speed
+                    // and stopping distance the same at every frame rate
+                    let effective_time = damping_factor.effective_time(delta_time);
+                    linear_velocity.x += direction.x * acceleration * effective_time;
+                    linear_velocity.y -= direction.y * acceleration * effective_time;
+                }
+                MovementAction::Dash(direction) => {
+                    let Some(mut dash_ability) = dash_ability else {
+                        continue;
+                    };
+                    if !dash_ability.cooldown.finished() {
+                        continue;
+                    }
+
+                    let Some(dash_direction) = Vector2::new(direction.x, -direction.y)
+                        .try_normalize()
+                        .or_else(|| linear_velocity.0.try_normalize())
+                    else {
+                        continue;
+                    };
+
+                    linear_velocity.0 = dash_direction * dash_ability.speed;
+                    dash_ability.cooldown.reset();
+                    commands.entity(entity).insert((
+                        Dashing(Timer::new(dash_ability.duration, TimerMode::Once)),
+                        Invulnerable::new(dash_ability.invulnerability),
+                    ));
                 }
             }
         }
     }
 }
 
-/// Slows down movement.
-fn apply_movement_damping(mut query: Query<(&MovementDampingFactor, &mut LinearVelocity)>) {
+/// Slows down movement, except while dashing.
+fn apply_movement_damping(
+    time: Res<Time>,
+    mut query: Query<(&MovementDampingFactor, &mut LinearVelocity), Without<Dashing>>,
+) {
+    let delta_time = time.delta_seconds_f64().adjust_precision();
+
     for (damping_factor, mut linear_velocity) in &mut query {
-        linear_velocity.x *= damping_factor.0;
-        linear_velocity.y *= damping_factor.0;
+        let decay = damping_factor.decay(delta_time);
+        linear_velocity.x *= decay;
+        linear_velocity.y *= decay;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{input::InputPlugin, time::TimeUpdateStrategy};
+
+    use super::*;
+
+    /// Velocity of a player holding right for half a second then releasing,
+    /// sampled every sixth of a second.
+    fn velocity_samples(fps: u32) -> Vec<Vector2> {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, CharacterControllerPlugin))
+            .add_state::<RunState>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / fps as f64,
+            )));
+        // The first update has no delta time
+        app.update();
+
+        let player = app
+            .world
+            .spawn((
+                MovementBundle::new(PLAYER_ACCELERATION, PLAYER_DAMPING),
+                LinearVelocity::ZERO,
+            ))
+            .id();
+
+        let frames_per_sample = fps / 6;
+        let mut samples = Vec::new();
+
+        for frame in 1..=fps {
+            if frame <= fps / 2 {
+                app.world.send_event(MovementAction::Move(Vector2::X));
+            }
+            app.update();
+
+            if frame % frames_per_sample == 0 {
+                samples.push(app.world.get::<LinearVelocity>(player).unwrap().0);
+            }
+        }
+
+        samples
+    }
+
+    #[test]
+    fn movement_is_frame_rate_independent() {
+        let reference = velocity_samples(60);
+
+        for fps in [30, 144] {
+            for (sample, expected) in velocity_samples(fps).iter().zip(&reference) {
+                assert!(
+                    (*sample - *expected).length() < 0.1,
+                    "{fps} FPS: velocity {sample} differs from {expected} at 60 FPS"
+                );
+            }
+        }
     }
 }
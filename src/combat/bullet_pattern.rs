@@ -0,0 +1,264 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::Deserialize;
+
+use crate::{layers::Layer, ron_asset::RonAssetPlugin};
+
+use super::*;
+
+pub(super) struct BulletPatternPlugin;
+
+impl Plugin for BulletPatternPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<BulletPattern>::new(&["pattern.ron"]))
+            .add_systems(Update, fire_bullet_patterns);
+    }
+}
+
+/// Volleys of projectiles fired in a loop, loaded from `.pattern.ron` files
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct BulletPattern {
+    /// Distance within which a target must be for the pattern to fire
+    pub range: f32,
+    /// Delay in seconds between the last volley and the first one of the next loop
+    pub cooldown: f32,
+    pub volleys: Vec<Volley>,
+    pub projectile: ProjectileSpec,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Volley {
+    /// Delay in seconds after the previous volley, bursts are several volleys with short delays
+    #[serde(default)]
+    pub delay: f32,
+    pub shape: VolleyShape,
+}
+
+/// Directions of the projectiles of a volley, angles are in degrees
+#[derive(Deserialize, Debug, Clone)]
+pub enum VolleyShape {
+    /// A single projectile toward the target
+    Aimed,
+    /// `count` projectiles evenly spread over `angle` around the direction of the target
+    Spread { count: u32, angle: f32 },
+    /// `count` projectiles evenly spread around the shooter
+    Ring { count: u32 },
+    /// A ring that turns by `step` every time it is fired
+    Spiral { count: u32, step: f32 },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProjectileSpec {
+    pub damage: i32,
+    pub speed: f32,
+    pub radius: f32,
+    /// Sprite, relative to the assets folder
+    pub sprite: String,
+    /// Seconds before the projectile disappears if it hit nothing
+    pub lifetime: f32,
+    /// Status effects applied to the targets hit
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+}
+
+impl VolleyShape {
+    /// Directions of the projectiles, `aim` is the direction toward the target
+    /// and `fired` is how many times this volley was fired before
+    pub fn directions(&self, aim: Vec2, fired: u32) -> Vec<Vec2> {
+        let evenly_spread = |count: u32, arc: f32, start: f32| {
+            (0..count)
+                .map(|i| Vec2::from_angle(start + arc * i as f32 / count as f32))
+                .collect()
+        };
+
+        match *self {
+            VolleyShape::Aimed => vec![aim],
+            VolleyShape::Spread { count, angle } => {
+                let angle = angle.to_radians();
+                let start = aim.y.atan2(aim.x) - angle / 2.;
+                // both edges of the spread get a projectile
+                (0..count)
+                    .map(|i| {
+                        let offset = if count > 1 {
+                            angle * i as f32 / (count - 1) as f32
+                        } else {
+                            angle / 2.
+                        };
+                        Vec2::from_angle(start + offset)
+                    })
+                    .collect()
+            }
+            VolleyShape::Ring { count } => evenly_spread(count, TAU, 0.),
+            VolleyShape::Spiral { count, step } => {
+                evenly_spread(count, TAU, (step * fired as f32).to_radians())
+            }
+        }
+    }
+}
+
+/// Fires a [`BulletPattern`] at the closest [`Hurtbox`] of the other team
+#[derive(Component)]
+pub struct Shooter {
+    pub team: Team,
+    pub pattern: Handle<BulletPattern>,
+    next_volley: usize,
+    /// Number of times each volley was fired, used by spirals
+    fired: Vec<u32>,
+    timer: Timer,
+}
+
+impl Shooter {
+    pub fn new(team: Team, pattern: Handle<BulletPattern>) -> Self {
+        Self {
+            team,
+            pattern,
+            next_volley: 0,
+            fired: Vec::new(),
+            timer: Timer::default(),
+        }
+    }
+}
+
+fn fire_bullet_patterns(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    patterns: Res<Assets<BulletPattern>>,
+    friendly_fire: Res<FriendlyFire>,
+    mut shooter_query: Query<(Entity, &mut Shooter, &GlobalTransform)>,
+    target_query: Query<(&Hurtbox, &GlobalTransform), Without<Shooter>>,
+) {
+    for (shooter_entity, mut shooter, shooter_transform) in shooter_query.iter_mut() {
+        let Some(pattern) = patterns.get(&shooter.pattern) else {
+            continue;
+        };
+        if pattern.volleys.is_empty() {
+            continue;
+        }
+
+        let position = shooter_transform.translation().truncate();
+        let Some(target) = target_query
+            .iter()
+            .filter(|(hurtbox, _)| hurtbox.team != shooter.team)
+            .map(|(_, transform)| transform.translation().truncate())
+            .filter(|target| target.distance(position) <= pattern.range)
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+        else {
+            continue;
+        };
+
+        if !shooter.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let index = shooter.next_volley % pattern.volleys.len();
+        shooter.fired.resize(pattern.volleys.len(), 0);
+        let aim = (target - position).try_normalize().unwrap_or(Vec2::X);
+        let volley = &pattern.volleys[index];
+
+        for direction in volley.shape.directions(aim, shooter.fired[index]) {
+            spawn_projectile(
+                &mut commands,
+                &asset_server,
+                &pattern.projectile,
+                shooter.team,
+                shooter_entity,
+                friendly_fire.0,
+                position,
+                direction,
+            );
+        }
+
+        shooter.fired[index] += 1;
+        shooter.next_volley = index + 1;
+        let delay = match pattern.volleys.get(shooter.next_volley) {
+            Some(next) => next.delay,
+            None => pattern.cooldown,
+        };
+        shooter.timer = Timer::new(Duration::from_secs_f32(delay), TimerMode::Once);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_projectile(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    projectile: &ProjectileSpec,
+    team: Team,
+    owner: Entity,
+    friendly_fire: bool,
+    position: Vec2,
+    direction: Vec2,
+) -> Entity {
+    let layers = match team {
+        Team::Player => Layer::player_projectile(friendly_fire),
+        Team::Enemy => Layer::enemy_projectile(friendly_fire),
+    };
+
+    commands
+        .spawn((
+            Attack::new(projectile.damage),
+            Hitbox::new(team).with_owner(owner),
+            Collider::ball(projectile.radius),
+            layers,
+            Sensor,
+            RigidBody::Dynamic,
+            LinearVelocity(projectile.speed * direction),
+            LockedAxes::ROTATION_LOCKED,
+            Lifetime::new(Duration::from_secs_f32(projectile.lifetime)),
+            OnHitEffects(projectile.effects.clone()),
+            SpriteBundle {
+                texture: asset_server.load(&projectile.sprite),
+                transform: Transform::from_translation(position.extend(0.)),
+                ..default()
+            },
+            Projectile,
+        ))
+        .id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_patterns_are_valid() {
+        for entry in std::fs::read_dir("assets/patterns").unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            if let Err(error) = ron::from_str::<BulletPattern>(&content) {
+                panic!("{}: {error}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn spread_covers_the_whole_angle_around_the_aim() {
+        let directions = VolleyShape::Spread {
+            count: 3,
+            angle: 90.,
+        }
+        .directions(Vec2::Y, 0);
+
+        assert_eq!(directions.len(), 3);
+        assert!((directions[0].angle_between(Vec2::Y).abs() - 45f32.to_radians()).abs() < 1e-4);
+        assert!(directions[1].distance(Vec2::Y) < 1e-4);
+        assert!((directions[2].angle_between(Vec2::Y).abs() - 45f32.to_radians()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn spiral_turns_every_time_it_is_fired() {
+        let shape = VolleyShape::Spiral {
+            count: 4,
+            step: 10.,
+        };
+
+        let first = shape.directions(Vec2::X, 0);
+        let second = shape.directions(Vec2::X, 1);
+
+        assert!(first[0].distance(Vec2::X) < 1e-4);
+        assert!((first[0].angle_between(second[0]) - 10f32.to_radians()).abs() < 1e-4);
+    }
+}
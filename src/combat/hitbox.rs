@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::prelude::*;
+
+use crate::layers::Layer;
+
+use super::*;
+
+pub(super) struct HitboxPlugin;
+
+impl Plugin for HitboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                projectile_terrain_collisions,
+                deflect_projectiles,
+                despawn_projectiles_on_hit.after(resolve_hits),
+            ),
+        );
+    }
+}
+
+/// Side an entity fights for, hitboxes only hit hurtboxes of the other team
+/// unless [`FriendlyFire`] is enabled
+#[derive(Component, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Team {
+    Player,
+    /// Anything that is not the player is hostile
+    #[default]
+    Enemy,
+}
+
+/// Deals the damage of the entity's [`Attack`] to the [`Hurtbox`]es its collider overlaps
+#[derive(Component, Default)]
+pub struct Hitbox {
+    pub team: Team,
+    /// Entity that created the hitbox, it is never hit by it
+    pub owner: Option<Entity>,
+    /// Delay before the same target can be hit again, each target is only hit once if `None`
+    pub rehit_delay: Option<Duration>,
+    /// Elapsed time at which each target was last hit
+    hits: HashMap<Entity, Duration>,
+}
+
+impl Hitbox {
+    pub fn new(team: Team) -> Self {
+        Self { team, ..default() }
+    }
+
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_rehit_delay(mut self, rehit_delay: Duration) -> Self {
+        self.rehit_delay = Some(rehit_delay);
+        self
+    }
+
+    fn can_hit(&self, target: Entity, now: Duration) -> bool {
+        match (self.hits.get(&target), self.rehit_delay) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(&last_hit), Some(rehit_delay)) => now >= last_hit + rehit_delay,
+        }
+    }
+}
+
+/// Receives damage from the [`Hitbox`]es overlapping the entity's collider
+#[derive(Component, Default)]
+pub struct Hurtbox {
+    pub team: Team,
+}
+
+impl Hurtbox {
+    pub fn new(team: Team) -> Self {
+        Self { team }
+    }
+}
+
+#[derive(Component)]
+pub struct Projectile;
+
+/// Number of targets a [`Projectile`] goes through before it is destroyed
+#[derive(Component)]
+pub struct Pierce(pub u32);
+
+/// Sends back the projectiles of other teams that overlap the entity's [`Hitbox`]
+#[derive(Component)]
+pub struct Deflector;
+
+/// Turns every hitbox overlapping a hurtbox into a [`DamageEvent`]
+pub(super) fn resolve_hits(
+    time: Res<Time>,
+    collisions: Res<Collisions>,
+    friendly_fire: Res<FriendlyFire>,
+    mut hitbox_query: Query<(&mut Hitbox, &Attack)>,
+    hurtbox_query: Query<&Hurtbox, Without<Invulnerable>>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+) {
+    let now = time.elapsed();
+
+    for contacts in collisions
+        .iter()
+        .filter(|contacts| contacts.during_current_frame)
+    {
+        for (source, target) in [
+            (contacts.entity1, contacts.entity2),
+            (contacts.entity2, contacts.entity1),
+        ] {
+            let (Ok((mut hitbox, attack)), Ok(hurtbox)) =
+                (hitbox_query.get_mut(source), hurtbox_query.get(target))
+            else {
+                continue;
+            };
+
+            if hitbox.owner == Some(target)
+                || (hitbox.team == hurtbox.team && !friendly_fire.0)
+                || !hitbox.can_hit(target, now)
+            {
+                continue;
+            }
+
+            hitbox.hits.insert(target, now);
+            damage_event_writer.send(DamageEvent {
+                source,
+                target,
+                amount: attack.amount,
+                effect: None,
+            });
+        }
+    }
+}
+
+/// Reverses deflected projectiles, which then belong to the deflector's team
+/// and collide with its layers.
+#[allow(clippy::type_complexity)]
+fn deflect_projectiles(
+    collisions: Res<Collisions>,
+    deflector_query: Query<(&Hitbox, &CollisionLayers), (With<Deflector>, Without<Projectile>)>,
+    mut projectile_query: Query<
+        (&mut Hitbox, &mut CollisionLayers, &mut LinearVelocity),
+        With<Projectile>,
+    >,
+) {
+    for contacts in collisions
+        .iter()
+        .filter(|contacts| contacts.during_current_frame)
+    {
+        for (deflector, projectile) in [
+            (contacts.entity1, contacts.entity2),
+            (contacts.entity2, contacts.entity1),
+        ] {
+            let (
+                Ok((deflector_hitbox, deflector_layers)),
+                Ok((mut hitbox, mut layers, mut linear_velocity)),
+            ) = (
+                deflector_query.get(deflector),
+                projectile_query.get_mut(projectile),
+            )
+            else {
+                continue;
+            };
+
+            if hitbox.team == deflector_hitbox.team {
+                continue;
+            }
+
+            *hitbox = Hitbox {
+                team: deflector_hitbox.team,
+                owner: deflector_hitbox.owner,
+                rehit_delay: hitbox.rehit_delay,
+                hits: default(),
+            };
+            *layers = *deflector_layers;
+            linear_velocity.0 = -linear_velocity.0;
+        }
+    }
+}
+
+fn despawn_projectiles_on_hit(
+    mut commands: Commands,
+    mut damage_event_reader: EventReader<DamageEvent>,
+    mut projectile_query: Query<Option<&mut Pierce>, With<Projectile>>,
+) {
+    for event in damage_event_reader.read() {
+        let Ok(pierce) = projectile_query.get_mut(event.source) else {
+            continue;
+        };
+
+        match pierce {
+            Some(mut pierce) if pierce.0 > 0 => pierce.0 -= 1,
+            _ => {
+                if let Some(projectile) = commands.get_entity(event.source) {
+                    projectile.despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+/// Collision layers only report projectiles against what they can hit,
+/// so a projectile is destroyed by any terrain it touches.
+fn projectile_terrain_collisions(
+    mut commands: Commands,
+    mut collision_event_reader: EventReader<CollisionStarted>,
+    layers_query: Query<&CollisionLayers>,
+    projectile_query: Query<With<Projectile>>,
+) {
+    for &CollisionStarted(entity1, entity2) in collision_event_reader.read() {
+        for (entity1, entity2) in [(entity1, entity2), (entity2, entity1)] {
+            if projectile_query.contains(entity1)
+                && layers_query
+                    .get(entity2)
+                    .is_ok_and(|layers| layers.contains_group(Layer::Terrain))
+            {
+                commands.entity(entity1).despawn_recursive();
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use self::{bullet_pattern::*, hitbox::*, status_effect::*};
+use crate::player::Player;
+
+pub mod bullet_pattern;
+pub mod hitbox;
+pub mod status_effect;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((HitboxPlugin, BulletPatternPlugin, StatusEffectPlugin))
+            .init_resource::<FriendlyFire>()
+            .add_event::<DamageEvent>()
+            .add_systems(
+                Update,
+                (
+                    tick_invulnerability,
+                    tick_lifetime,
+                    (resolve_hits, apply_damage, despawn_dead).chain(),
+                    apply_knockback.after(resolve_hits),
+                ),
+            );
+    }
+}
+
+/// Whether hitboxes can hit the other members of the team they belong to,
+/// such as mobs caught in the bullets of another mob. Hitboxes never hit their owner,
+/// so the player, alone on its team, is not hurt by its own attacks either way.
+#[derive(Resource, Default)]
+pub struct FriendlyFire(pub bool);
+
+// pub enum DamageType {
+//     Physical,
+//     Magical,
+//     True,
+// }
+
+/// Damage dealt by a [`Hitbox`]
+#[derive(Component, Default)]
+pub struct Attack {
+    // pub damage_type: DamageType,
+    pub amount: i32,
+}
+
+impl Attack {
+    pub fn new(amount: i32) -> Self {
+        Attack { amount }
+    }
+}
+
+/// Speed at which the targets hit by the entity's [`Hitbox`] are pushed away
+#[derive(Component, Default)]
+pub struct Knockback(pub f32);
+
+/// An event sent when `source` deals damage to `target`
+#[derive(Event)]
+pub struct DamageEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    /// Status effect dealing the damage over time, `None` for hits
+    pub effect: Option<StatusKind>,
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+
+    /// Restores up to `amount` health points without going over the maximum
+    pub fn heal(&mut self, amount: i32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+/// A single health point, any hit is lethal
+impl Default for Health {
+    fn default() -> Self {
+        Health::new(1)
+    }
+}
+
+/// Damage is ignored while this component is present
+#[derive(Component)]
+pub struct Invulnerable(Timer);
+
+impl Invulnerable {
+    pub fn new(duration: Duration) -> Self {
+        Invulnerable(Timer::new(duration, TimerMode::Once))
+    }
+}
+
+/// The entity is despawned once the timer finishes
+#[derive(Component)]
+pub struct Lifetime(Timer);
+
+impl Lifetime {
+    pub fn new(duration: Duration) -> Self {
+        Lifetime(Timer::new(duration, TimerMode::Once))
+    }
+}
+
+fn tick_lifetime(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Lifetime)>,
+) {
+    for (entity, mut lifetime) in query.iter_mut() {
+        if lifetime.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+pub fn apply_damage(
+    mut damage_event_reader: EventReader<DamageEvent>,
+    mut health_query: Query<&mut Health, Without<Invulnerable>>,
+) {
+    for event in damage_event_reader.read() {
+        if let Ok(mut health) = health_query.get_mut(event.target) {
+            health.current -= event.amount;
+        }
+    }
+}
+
+/// Pushes targets away from the owner of the hitbox that hit them, or from the hitbox itself
+fn apply_knockback(
+    mut damage_event_reader: EventReader<DamageEvent>,
+    source_query: Query<(&Knockback, &Hitbox, &GlobalTransform)>,
+    transform_query: Query<&GlobalTransform>,
+    mut target_query: Query<&mut LinearVelocity, Without<Invulnerable>>,
+) {
+    for event in damage_event_reader
+        .read()
+        .filter(|event| event.effect.is_none())
+    {
+        let (Ok((knockback, hitbox, source_transform)), Ok(target_transform)) = (
+            source_query.get(event.source),
+            transform_query.get(event.target),
+        ) else {
+            continue;
+        };
+        let Ok(mut linear_velocity) = target_query.get_mut(event.target) else {
+            continue;
+        };
+
+        let origin = hitbox
+            .owner
+            .and_then(|owner| transform_query.get(owner).ok())
+            .unwrap_or(source_transform)
+            .translation()
+            .truncate();
+        let direction = (target_transform.translation().truncate() - origin)
+            .try_normalize()
+            .unwrap_or(Vec2::X);
+
+        linear_velocity.0 += knockback.0 * direction;
+    }
+}
+
+/// Systems reacting to deaths run after [`apply_damage`] and before this system,
+/// while the dead entities still exist
+///
+/// The player is kept, its death ends the run instead.
+pub fn despawn_dead(
+    mut commands: Commands,
+    query: Query<(Entity, &Health), (Changed<Health>, Without<Player>)>,
+) {
+    for (entity, health) in query.iter() {
+        if health.is_dead() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
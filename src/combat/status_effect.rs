@@ -0,0 +1,409 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{
+    character_controller_dynamic::{AccelerationScale, Stunned},
+    stats::{Modifier, ModifierSource, Stat, Stats},
+};
+
+use super::*;
+
+pub(super) struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                apply_on_hit_effects.after(resolve_hits),
+                tick_status_effects.before(apply_damage),
+                tint_affected_sprites,
+            ),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum StatusKind {
+    /// Damage over time
+    Burn,
+    /// Scales down movement acceleration
+    Slow,
+    /// Movement actions are ignored
+    Stun,
+    /// Damage over time that never kills
+    Poison,
+}
+
+impl StatusKind {
+    pub fn tint(&self) -> Color {
+        match self {
+            StatusKind::Burn => Color::rgb(1., 0.55, 0.35),
+            StatusKind::Slow => Color::rgb(0.55, 0.75, 1.),
+            StatusKind::Stun => Color::rgb(1., 1., 0.5),
+            StatusKind::Poison => Color::rgb(0.55, 1., 0.45),
+        }
+    }
+}
+
+/// What happens when an effect is applied to an entity already under the same effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Stacking {
+    /// The duration restarts and the strongest intensity is kept
+    Refresh,
+    /// The intensity adds up to `max_stacks` times, the duration restarts
+    Intensity { max_stacks: u32 },
+    /// Every application runs on its own
+    Independent,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Damage per second for burn and poison, fraction of the acceleration removed by slow,
+    /// unused by stun
+    #[serde(default)]
+    pub intensity: f32,
+    /// Duration in seconds
+    pub duration: f32,
+    pub stacking: Stacking,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, intensity: f32, duration: Duration, stacking: Stacking) -> Self {
+        Self {
+            kind,
+            intensity,
+            duration: duration.as_secs_f32(),
+            stacking,
+        }
+    }
+}
+
+/// Status effects applied by the entity's [`Hitbox`] to the targets it damages
+#[derive(Component, Default, Clone)]
+pub struct OnHitEffects(pub Vec<StatusEffect>);
+
+/// Fraction of the duration of each kind of status effect ignored by the entity,
+/// `1` makes it immune
+#[derive(Component, Default, Clone, Debug)]
+pub struct Resistances(pub HashMap<StatusKind, f32>);
+
+impl Resistances {
+    /// The effect as it applies to the entity, `None` if the entity is immune
+    pub fn resist(&self, mut effect: StatusEffect) -> Option<StatusEffect> {
+        let resistance = self.0.get(&effect.kind).copied().unwrap_or_default();
+        if resistance >= 1. {
+            return None;
+        }
+        effect.duration *= 1. - resistance.max(0.);
+        Some(effect)
+    }
+}
+
+/// Status effects an entity is under
+#[derive(Component, Default)]
+pub struct StatusEffects {
+    active: Vec<ActiveEffect>,
+}
+
+struct ActiveEffect {
+    effect: StatusEffect,
+    /// Entity credited for the damage dealt by the effect
+    source: Entity,
+    stacks: u32,
+    timer: Timer,
+    /// Damage over time not dealt yet because it is less than a health point
+    pending_damage: f32,
+}
+
+impl ActiveEffect {
+    fn intensity(&self) -> f32 {
+        self.effect.intensity * self.stacks as f32
+    }
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect, source: Entity) {
+        let duration = Duration::from_secs_f32(effect.duration);
+        let existing = self.active.iter_mut().find(|active| {
+            active.effect.kind == effect.kind && active.effect.stacking == effect.stacking
+        });
+
+        match (effect.stacking, existing) {
+            (Stacking::Refresh, Some(active)) => {
+                active.effect.intensity = active.effect.intensity.max(effect.intensity);
+                active.source = source;
+                active.timer = Timer::new(duration, TimerMode::Once);
+            }
+            (Stacking::Intensity { max_stacks }, Some(active)) => {
+                active.stacks = (active.stacks + 1).min(max_stacks.max(1));
+                active.source = source;
+                active.timer = Timer::new(duration, TimerMode::Once);
+            }
+            _ => self.active.push(ActiveEffect {
+                effect,
+                source,
+                stacks: 1,
+                timer: Timer::new(duration, TimerMode::Once),
+                pending_damage: 0.,
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|active| active.effect.kind == kind)
+    }
+
+    /// Sum of the intensities of the effects of the given kind, stacks included
+    pub fn intensity(&self, kind: StatusKind) -> f32 {
+        self.active
+            .iter()
+            .filter(|active| active.effect.kind == kind)
+            .map(ActiveEffect::intensity)
+            .sum()
+    }
+
+    /// Factor applied to the movement acceleration
+    pub fn acceleration_scale(&self) -> f32 {
+        self.active
+            .iter()
+            .filter(|active| active.effect.kind == StatusKind::Slow)
+            .map(|active| (1. - active.intensity()).clamp(0., 1.))
+            .product()
+    }
+
+    /// Tint of the most recently applied effect
+    pub fn tint(&self) -> Color {
+        self.active
+            .last()
+            .map_or(Color::WHITE, |active| active.effect.kind.tint())
+    }
+}
+
+fn apply_on_hit_effects(
+    mut damage_event_reader: EventReader<DamageEvent>,
+    source_query: Query<(&OnHitEffects, &Hitbox)>,
+    mut target_query: Query<(&mut StatusEffects, Option<&Resistances>)>,
+) {
+    for event in damage_event_reader.read() {
+        if event.effect.is_some() {
+            continue;
+        }
+        let (Ok((on_hit_effects, hitbox)), Ok((mut status_effects, resistances))) = (
+            source_query.get(event.source),
+            target_query.get_mut(event.target),
+        ) else {
+            continue;
+        };
+
+        for effect in &on_hit_effects.0 {
+            let effect = match resistances {
+                Some(resistances) => resistances.resist(effect.clone()),
+                None => Some(effect.clone()),
+            };
+            if let Some(effect) = effect {
+                status_effects.apply(effect, hitbox.owner.unwrap_or(event.source));
+            }
+        }
+    }
+}
+
+/// Expires effects, deals damage over time and slows down or stuns character controllers
+///
+/// Slows are a modifier of the [`Stats`] of the entities that have some,
+/// and an [`AccelerationScale`] for the others.
+#[allow(clippy::type_complexity)]
+fn tick_status_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut StatusEffects,
+        Option<&Health>,
+        Option<&AccelerationScale>,
+        Option<&mut Stats>,
+        Has<Stunned>,
+    )>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+) {
+    for (entity, mut status_effects, health, acceleration_scale, stats, stunned) in query.iter_mut()
+    {
+        if status_effects.is_empty() && acceleration_scale.is_none() && !stunned {
+            continue;
+        }
+
+        let mut poison_budget = health.map_or(0, |health| health.current - 1);
+        for active in status_effects.active.iter_mut() {
+            active.timer.tick(time.delta());
+
+            if !matches!(active.effect.kind, StatusKind::Burn | StatusKind::Poison) {
+                continue;
+            }
+            active.pending_damage += active.intensity() * time.delta_seconds();
+            let mut amount = active.pending_damage.floor() as i32;
+            if amount < 1 {
+                continue;
+            }
+            active.pending_damage -= amount as f32;
+
+            if active.effect.kind == StatusKind::Poison {
+                amount = amount.min(poison_budget);
+                poison_budget -= amount;
+                if amount < 1 {
+                    continue;
+                }
+            }
+            damage_event_writer.send(DamageEvent {
+                source: active.source,
+                target: entity,
+                amount,
+                effect: Some(active.effect.kind),
+            });
+        }
+        status_effects
+            .active
+            .retain(|active| !active.timer.finished());
+
+        match (status_effects.has(StatusKind::Stun), stunned) {
+            (true, false) => {
+                commands.entity(entity).insert(Stunned);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<Stunned>();
+            }
+            _ => (),
+        }
+
+        let scale = status_effects.acceleration_scale();
+        if let Some(mut stats) = stats {
+            let source = ModifierSource::Status(StatusKind::Slow);
+            let modifiers = if scale < 1. {
+                vec![Modifier::multiplicative(Stat::MoveAcceleration, scale - 1.)]
+            } else {
+                Vec::new()
+            };
+            // Only touched when the slow changes so that stats are not recomputed every frame
+            if stats.modifiers(source) != modifiers {
+                stats.set_modifiers(source, modifiers);
+            }
+        } else if scale < 1. {
+            commands.entity(entity).insert(AccelerationScale(scale));
+        } else if acceleration_scale.is_some() {
+            commands.entity(entity).remove::<AccelerationScale>();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn tint_affected_sprites(
+    mut query: Query<
+        (
+            &StatusEffects,
+            Option<&mut Sprite>,
+            Option<&mut TextureAtlasSprite>,
+        ),
+        Changed<StatusEffects>,
+    >,
+) {
+    for (status_effects, sprite, atlas_sprite) in query.iter_mut() {
+        let tint = status_effects.tint();
+        if let Some(mut sprite) = sprite {
+            sprite.color = tint;
+        }
+        if let Some(mut atlas_sprite) = atlas_sprite {
+            atlas_sprite.color = tint;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: Entity = Entity::PLACEHOLDER;
+
+    fn burn(stacking: Stacking) -> StatusEffect {
+        StatusEffect::new(StatusKind::Burn, 4., Duration::from_secs(2), stacking)
+    }
+
+    #[test]
+    fn refresh_keeps_a_single_effect() {
+        let mut status_effects = StatusEffects::default();
+        status_effects.apply(burn(Stacking::Refresh), SOURCE);
+        status_effects.active[0].timer.tick(Duration::from_secs(1));
+        status_effects.apply(burn(Stacking::Refresh), SOURCE);
+
+        assert_eq!(status_effects.active.len(), 1);
+        assert_eq!(status_effects.active[0].timer.elapsed(), Duration::ZERO);
+        assert_eq!(status_effects.intensity(StatusKind::Burn), 4.);
+    }
+
+    #[test]
+    fn intensity_stacks_up_to_the_maximum() {
+        let mut status_effects = StatusEffects::default();
+        for _ in 0..5 {
+            status_effects.apply(burn(Stacking::Intensity { max_stacks: 3 }), SOURCE);
+        }
+
+        assert_eq!(status_effects.active.len(), 1);
+        assert_eq!(status_effects.intensity(StatusKind::Burn), 12.);
+    }
+
+    #[test]
+    fn independent_effects_run_on_their_own() {
+        let mut status_effects = StatusEffects::default();
+        status_effects.apply(burn(Stacking::Independent), SOURCE);
+        status_effects.active[0].timer.tick(Duration::from_secs(1));
+        status_effects.apply(burn(Stacking::Independent), SOURCE);
+
+        assert_eq!(status_effects.active.len(), 2);
+        assert_eq!(
+            status_effects.active[0].timer.elapsed(),
+            Duration::from_secs(1)
+        );
+        assert_eq!(status_effects.intensity(StatusKind::Burn), 8.);
+    }
+
+    #[test]
+    fn slows_multiply() {
+        let mut status_effects = StatusEffects::default();
+        let slow = StatusEffect::new(
+            StatusKind::Slow,
+            0.5,
+            Duration::from_secs(1),
+            Stacking::Independent,
+        );
+        status_effects.apply(slow.clone(), SOURCE);
+        status_effects.apply(slow, SOURCE);
+
+        assert_eq!(status_effects.acceleration_scale(), 0.25);
+    }
+
+    #[test]
+    fn resistances_shorten_effects_or_prevent_them() {
+        let resistances = Resistances(
+            [(StatusKind::Burn, 0.25), (StatusKind::Stun, 1.)]
+                .into_iter()
+                .collect(),
+        );
+
+        let burn = resistances.resist(burn(Stacking::Refresh)).unwrap();
+        assert_eq!(burn.duration, 1.5);
+        assert_eq!(burn.intensity, 4.);
+
+        let stun = StatusEffect::new(
+            StatusKind::Stun,
+            0.,
+            Duration::from_secs(1),
+            Stacking::Refresh,
+        );
+        assert_eq!(resistances.resist(stun), None);
+    }
+}
@@ -1,20 +1,55 @@
+use std::marker::PhantomData;
+
 use bevy::{
+    ecs::system::SystemParam,
     prelude::*,
     utils::{HashMap, HashSet},
 };
 use bevy_ecs_ldtk::prelude::*;
-use bevy_xpbd_2d::components::{Collider, Friction, RigidBody};
+use bevy_xpbd_2d::components::{Collider, CollisionLayers, Friction, RigidBody};
+
+use super::{
+    grid_rects::{merge_cells, GridRect},
+    slopes::{merge_slopes, Slope},
+};
+use crate::layers::Layer;
 
 const COLLISION_FRICTION_COEFFICIENT: f32 = 0.9;
-const COLLISIONS_LAYER_ID: &str = "Collision";
+pub(super) const COLLISIONS_LAYER_ID: &str = "Collision";
+/// Value of the wall cells of the collisions layer
+pub(super) const COLLIDER_VALUE: i32 = 1;
+/// Value of the door cells of the collisions layer
+pub(super) const DOOR_VALUE: i32 = 2;
+/// Values of the 45° wall cells of the collisions layer, see [`Slope`]
+pub(super) const SLOPE_VALUES: [(i32, Slope); 4] = [
+    (3, Slope::BottomLeft),
+    (4, Slope::BottomRight),
+    (5, Slope::TopLeft),
+    (6, Slope::TopRight),
+];
 
 pub(super) struct CollisionsPlugin;
 
 impl Plugin for CollisionsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (spawn_wall_collision, hide_collisions_layer))
-            .register_ldtk_int_cell_for_layer::<CollisionTileBundle>(COLLISIONS_LAYER_ID, 1)
-            .register_ldtk_int_cell_for_layer::<DoorTileBundle>(COLLISIONS_LAYER_ID, 2);
+        app.add_systems(
+            Update,
+            (
+                spawn_merged_colliders::<CollisionTile>,
+                spawn_merged_colliders::<DoorTile>,
+                spawn_slope_colliders,
+                hide_collisions_layer,
+            ),
+        )
+        .register_ldtk_int_cell_for_layer::<CollisionTileBundle>(
+            COLLISIONS_LAYER_ID,
+            COLLIDER_VALUE,
+        )
+        .register_ldtk_int_cell_for_layer::<DoorTileBundle>(COLLISIONS_LAYER_ID, DOOR_VALUE);
+
+        for (value, _) in SLOPE_VALUES {
+            app.register_ldtk_int_cell_for_layer::<SlopeTileBundle>(COLLISIONS_LAYER_ID, value);
+        }
     }
 }
 
@@ -29,9 +64,43 @@ pub struct CollisionTileBundle {
     pub collision_tile: CollisionTile,
 }
 
+/// Wall filling half of its cell, read from one of the [`SLOPE_VALUES`]
+#[derive(Component)]
+pub struct SlopeTile(pub Slope);
+
+impl From<IntGridCell> for SlopeTile {
+    fn from(int_grid_cell: IntGridCell) -> Self {
+        let (_, slope) = SLOPE_VALUES
+            .into_iter()
+            .find(|(value, _)| *value == int_grid_cell.value)
+            .expect("slope tiles should only be registered for slope values");
+        SlopeTile(slope)
+    }
+}
+
+#[derive(Bundle, LdtkIntCell)]
+pub struct SlopeTileBundle {
+    #[from_int_grid_cell]
+    pub slope_tile: SlopeTile,
+}
+
 #[derive(Default, Component)]
 pub struct DoorTile;
 
+/// Collider covering adjacent [`DoorTile`]s, open unless its layers are [`Layer::door`]
+#[derive(Component)]
+pub struct Door;
+
+impl Door {
+    pub fn open() -> CollisionLayers {
+        CollisionLayers::none()
+    }
+
+    pub fn closed() -> CollisionLayers {
+        Layer::door()
+    }
+}
+
 #[derive(Default, Bundle, LdtkIntCell)]
 pub struct DoorTileBundle {
     pub door_tile: DoorTile,
@@ -47,169 +116,259 @@ fn hide_collisions_layer(
     }
 }
 
-/// See https://github.com/Trouv/bevy_ecs_ldtk/blob/main/examples/platformer/systems.rs#L78
-/// Spawns xpbd collisions for the walls of a level
-///
-/// The algorithm used here is a nice compromise between simplicity, speed,
-/// and a small number of rectangle colliders.
-/// In basic terms, it will:
-/// 1. consider where the walls are
-/// 2. combine wall tiles into flat "plates" in each individual row
-/// 3. combine the plates into rectangles across multiple rows wherever possible
-/// 4. spawn colliders for each rectangle
-///
-/// For general rectilinear polygons, the problem of finding a minimum rectangle covering is NP-hard.
-/// See the following article for bounds:
-/// Kumar, V. S. A.; Ramesh, H. (2003). "Covering Rectilinear Polygons with Axis-Parallel Rectangles".
-/// SIAM Journal on Computing. 32 (6): 1509. CiteSeerX 10.1.1.20.2664. doi:10.1137/s0097539799358835
-fn spawn_wall_collision(
-    mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<CollisionTile>>,
-    parent_query: Query<&Parent, Without<CollisionTile>>,
-    level_query: Query<(Entity, &LevelIid)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
-) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
+/// Tiles whose adjacent cells are merged into larger colliders,
+/// see [`spawn_merged_colliders`] and [`spawn_slope_colliders`]
+pub trait MergedCollider: Component {
+    type Bundle: Bundle;
+
+    /// Components added alongside the collider of each merged rectangle
+    fn bundle() -> Self::Bundle;
+}
+
+impl MergedCollider for CollisionTile {
+    type Bundle = (RigidBody, Friction, Terrain, CollisionLayers);
+
+    fn bundle() -> Self::Bundle {
+        (
+            RigidBody::Static,
+            Friction::new(COLLISION_FRICTION_COEFFICIENT),
+            Terrain,
+            Layer::terrain(),
+        )
     }
+}
+
+/// Slopes are walls too
+impl MergedCollider for SlopeTile {
+    type Bundle = <CollisionTile as MergedCollider>::Bundle;
 
-    /// A simple rectangle type representing a wall of any size
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
+    fn bundle() -> Self::Bundle {
+        CollisionTile::bundle()
     }
+}
+
+impl MergedCollider for DoorTile {
+    type Bundle = (RigidBody, Door, CollisionLayers);
+
+    fn bundle() -> Self::Bundle {
+        (RigidBody::Static, Door, Door::open())
+    }
+}
+
+/// Collider spawned for the tiles marked with `T`
+#[derive(Component)]
+pub struct MergedColliderOf<T: MergedCollider> {
+    /// Identifier of the layer of the tiles
+    pub layer: String,
+    marker: PhantomData<T>,
+}
 
-    // Consider where the walls are
+/// See https://github.com/Trouv/bevy_ecs_ldtk/blob/main/examples/platformer/systems.rs#L78
+/// Spawns xpbd collisions for the tiles marked with `T` in a level
+///
+/// Tiles are merged into rectangles with [`merge_cells`] separately for each layer,
+/// which may have its own grid size and offset, then a collider is spawned for each rectangle.
+/// The tiles of a level are added again when it respawns, e.g. when the map is hot reloaded,
+/// its previous colliders are then replaced.
+pub(super) fn spawn_merged_colliders<T: MergedCollider>(
+    tile_query: Query<(&GridCoords, &Parent), Added<T>>,
+    mut layer_colliders: LayerColliders<T>,
+) {
+    // Consider where the tiles are
     // storing them as GridCoords in a HashSet for quick, easy lookup
     //
-    // The key of this map will be the entity of the level the wall belongs to.
+    // The key of this map will be the entity of the layer the tile belongs to.
     // This has two consequences in the resulting collision entities:
-    // 1. it forces the walls to be split along level boundaries
-    // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    for (&grid_coords, parent) in wall_query.iter() {
-        // An intgrid tile's direct parent will be a layer entity, not the level entity
-        // To get the level entity, you need the tile's grandparent.
-        // This is where parent_query comes in.
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
-        }
+    // 1. it forces the colliders to be split along level boundaries
+    // 2. the cells are merged on the grid of their own layer
+    let mut layer_to_tile_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
+
+    // An intgrid tile's direct parent will be a layer entity, not the level entity
+    for (&grid_coords, parent) in tile_query.iter() {
+        layer_to_tile_locations
+            .entry(parent.get())
+            .or_default()
+            .insert(grid_coords);
     }
 
-    if !wall_query.is_empty() {
-        for (level_entity, level_iid) in level_query.iter() {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let ldtk_project = ldtk_project_assets
-                    .get(ldtk_projects.single())
-                    .expect("Project should be loaded if level has spawned");
-
-                let level = ldtk_project
-                    .as_standalone()
-                    .get_loaded_level_by_iid(&level_iid.to_string())
-                    .expect("Spawned level should exist in LDtk project");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level.layer_instances()[0];
-
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
-
-                    plate_stack.push(row_plates);
-                }
+    for (layer_entity, layer_tiles) in layer_to_tile_locations {
+        layer_colliders.replace(layer_entity, |layer| {
+            let grid_size = layer.grid_size as f32;
+            merge_cells(layer.c_wid, layer.c_hei, |coords| {
+                layer_tiles.contains(&coords)
+            })
+            .into_iter()
+            .map(|rect| {
+                let collider = Collider::cuboid(
+                    rect.width() as f32 * grid_size,
+                    rect.height() as f32 * grid_size,
+                );
+                (rect, collider)
+            })
+            .collect()
+        });
+    }
+}
 
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
-                        }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| e.top += 1)
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
-                            });
-                    }
-                    prev_row = current_row;
-                }
+/// Spawns a triangle collider for each diagonal of [`SlopeTile`]s merged with [`merge_slopes`]
+fn spawn_slope_colliders(
+    slope_query: Query<(&GridCoords, &SlopeTile, &Parent), Added<SlopeTile>>,
+    wall_query: Query<(&GridCoords, &Parent), With<CollisionTile>>,
+    mut layer_colliders: LayerColliders<SlopeTile>,
+) {
+    let mut layer_to_slopes: HashMap<Entity, HashMap<GridCoords, Slope>> = HashMap::new();
+    for (&grid_coords, slope_tile, parent) in slope_query.iter() {
+        layer_to_slopes
+            .entry(parent.get())
+            .or_default()
+            .insert(grid_coords, slope_tile.0);
+    }
+
+    for (layer_entity, slopes) in layer_to_slopes {
+        let walls: HashSet<GridCoords> = wall_query
+            .iter()
+            .filter(|(_, parent)| parent.get() == layer_entity)
+            .map(|(&grid_coords, _)| grid_coords)
+            .collect();
+
+        layer_colliders.replace(layer_entity, |layer| {
+            let grid_size = layer.grid_size as f32;
+            merge_slopes(&slopes, |coords| walls.contains(&coords))
+                .into_iter()
+                .map(|run| {
+                    let [a, b, c] = run.vertices().map(|vertex| vertex * grid_size);
+                    (run.rect, Collider::triangle(a, b, c))
+                })
+                .collect()
+        });
+    }
+}
+
+/// Colliders spawned for the tiles marked with `T`, as children of the level of the tiles
+#[derive(SystemParam)]
+pub(super) struct LayerColliders<'w, 's, T: MergedCollider> {
+    commands: Commands<'w, 's>,
+    layer_query: Query<'w, 's, (&'static LayerMetadata, &'static Parent)>,
+    collider_query: Query<'w, 's, (Entity, &'static Parent, &'static MergedColliderOf<T>)>,
+    level_query: Query<'w, 's, &'static LevelIid>,
+    ldtk_projects: Query<'w, 's, &'static Handle<LdtkProject>>,
+    ldtk_project_assets: Res<'w, Assets<LdtkProject>>,
+}
 
-                commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32,
-                            ))
-                            .insert(RigidBody::Static)
-                            .insert(Friction::new(COLLISION_FRICTION_COEFFICIENT))
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(GlobalTransform::default())
-                            .insert(Terrain);
-                    }
-                });
+impl<T: MergedCollider> LayerColliders<'_, '_, T> {
+    /// Despawns the colliders previously spawned for the tiles of a layer,
+    /// then spawns the `colliders` covering rectangles of cells of the layer
+    fn replace(
+        &mut self,
+        layer_entity: Entity,
+        colliders: impl FnOnce(&LayerMetadata) -> Vec<(GridRect, Collider)>,
+    ) {
+        let Ok((layer, level_entity)) = self.layer_query.get(layer_entity) else {
+            return;
+        };
+        let level_entity = level_entity.get();
+        let Ok(level_iid) = self.level_query.get(level_entity) else {
+            return;
+        };
+
+        let ldtk_project = self
+            .ldtk_project_assets
+            .get(self.ldtk_projects.single())
+            .expect("Project should be loaded if level has spawned");
+        let level = ldtk_project
+            .get_raw_level_by_iid(level_iid.get())
+            .expect("Spawned level should exist in LDtk project");
+
+        for (collider, parent, merged_collider) in self.collider_query.iter() {
+            if parent.get() == level_entity && merged_collider.layer == layer.identifier {
+                self.commands.entity(collider).despawn_recursive();
             }
         }
+
+        let colliders = colliders(layer);
+        self.commands
+            .entity(level_entity)
+            .with_children(|level_builder| {
+                // Spawn colliders for every rectangle..
+                // Making the collider a child of the level serves two purposes:
+                // 1. Adjusts the transforms to be relative to the level for free
+                // 2. the colliders will be despawned automatically when levels unload
+                for (rect, collider) in colliders {
+                    level_builder.spawn((
+                        collider,
+                        T::bundle(),
+                        MergedColliderOf::<T> {
+                            layer: layer.identifier.clone(),
+                            marker: PhantomData,
+                        },
+                        Transform::from_translation(
+                            rect_center(rect, layer, level.px_hei).extend(0.),
+                        ),
+                        GlobalTransform::default(),
+                    ));
+                }
+            });
+    }
+}
+
+/// Center of a rectangle of cells of `layer`, relative to the bottom left corner of its level
+///
+/// Grid coordinates start from the bottom of the layer, which is shifted by its offset
+/// from the top left corner of the level in LDtk.
+fn rect_center(rect: GridRect, layer: &LayerMetadata, level_height: i32) -> Vec2 {
+    let grid_size = layer.grid_size as f32;
+    let layer_bottom =
+        level_height as f32 - layer.px_total_offset_y as f32 - layer.c_hei as f32 * grid_size;
+
+    Vec2::new(
+        layer.px_total_offset_x as f32 + (rect.left + rect.right + 1) as f32 * grid_size / 2.,
+        layer_bottom + (rect.bottom + rect.top + 1) as f32 * grid_size / 2.,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(grid_size: i32, c_wid: i32, c_hei: i32, offset: IVec2) -> LayerMetadata {
+        LayerMetadata::from(&LayerInstance {
+            grid_size,
+            c_wid,
+            c_hei,
+            px_total_offset_x: offset.x,
+            px_total_offset_y: offset.y,
+            ..default()
+        })
+    }
+
+    #[test]
+    fn rects_are_centered_on_their_cells() {
+        let rect = GridRect {
+            left: 2,
+            right: 3,
+            bottom: 0,
+            top: 0,
+        };
+
+        assert_eq!(
+            rect_center(rect, &layer(16, 35, 19, IVec2::ZERO), 304),
+            Vec2::new(48., 8.)
+        );
+    }
+
+    #[test]
+    fn rects_follow_the_grid_size_and_offset_of_their_layer() {
+        // The top left cell of a layer shifted by 4 pixels to the right and to the bottom
+        let rect = GridRect {
+            left: 0,
+            right: 0,
+            bottom: 37,
+            top: 37,
+        };
+
+        assert_eq!(
+            rect_center(rect, &layer(8, 70, 38, IVec2::new(4, 4)), 304),
+            Vec2::new(8., 296.)
+        );
     }
 }
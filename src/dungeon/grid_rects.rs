@@ -0,0 +1,167 @@
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::GridCoords;
+
+/// A rectangle of grid cells, all bounds are inclusive
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct GridRect {
+    pub left: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub top: i32,
+}
+
+impl GridRect {
+    pub fn width(&self) -> i32 {
+        self.right - self.left + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.top - self.bottom + 1
+    }
+
+    pub fn contains(&self, coords: GridCoords) -> bool {
+        (self.left..=self.right).contains(&coords.x) && (self.bottom..=self.top).contains(&coords.y)
+    }
+}
+
+/// Represents a wide group of cells that is 1 tile tall
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+/// Merges the filled cells of a `width` x `height` grid into a small number of rectangles
+///
+/// The algorithm used here is a nice compromise between simplicity, speed,
+/// and a small number of rectangles.
+/// In basic terms, it will:
+/// 1. combine filled cells into flat "plates" in each individual row
+/// 2. combine the plates into rectangles across multiple rows wherever possible
+///
+/// The rectangles cover exactly the filled cells and never overlap.
+///
+/// For general rectilinear polygons, the problem of finding a minimum rectangle covering is NP-hard.
+/// See the following article for bounds:
+/// Kumar, V. S. A.; Ramesh, H. (2003). "Covering Rectilinear Polygons with Axis-Parallel Rectangles".
+/// SIAM Journal on Computing. 32 (6): 1509. CiteSeerX 10.1.1.20.2664. doi:10.1137/s0097539799358835
+pub fn merge_cells(
+    width: i32,
+    height: i32,
+    is_filled: impl Fn(GridCoords) -> bool,
+) -> Vec<GridRect> {
+    // combine filled cells into flat "plates" in each individual row
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+
+    for y in 0..height {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width so the algorithm "terminates" plates that touch the right edge
+        for x in 0..width + 1 {
+            match (plate_start, x < width && is_filled(GridCoords { x, y })) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: x - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+
+        plate_stack.push(row_plates);
+    }
+
+    // combine "plates" into rectangles across multiple rows
+    let mut rect_builder: HashMap<Plate, GridRect> = HashMap::new();
+    let mut prev_row: Vec<Plate> = Vec::new();
+    let mut rects: Vec<GridRect> = Vec::new();
+
+    // an extra empty row so the algorithm "finishes" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (y, current_row) in plate_stack.into_iter().enumerate() {
+        for prev_plate in &prev_row {
+            if !current_row.contains(prev_plate) {
+                // remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_plate) {
+                    rects.push(rect);
+                }
+            }
+        }
+        for plate in &current_row {
+            rect_builder
+                .entry(plate.clone())
+                .and_modify(|e| e.top += 1)
+                .or_insert(GridRect {
+                    bottom: y as i32,
+                    top: y as i32,
+                    left: plate.left,
+                    right: plate.right,
+                });
+        }
+        prev_row = current_row;
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const MAX_SIZE: i32 = 24;
+
+    fn grid() -> impl Strategy<Value = (i32, i32, HashSet<GridCoords>)> {
+        (1..MAX_SIZE, 1..MAX_SIZE).prop_flat_map(|(width, height)| {
+            let cells = proptest::collection::hash_set(
+                (0..width, 0..height),
+                0..=(width * height) as usize,
+            )
+            .prop_map(|cells| {
+                cells
+                    .into_iter()
+                    .map(|(x, y)| GridCoords { x, y })
+                    .collect()
+            });
+            (Just(width), Just(height), cells)
+        })
+    }
+
+    #[test]
+    fn merges_full_grid_into_one_rectangle() {
+        assert_eq!(
+            merge_cells(4, 3, |_| true),
+            vec![GridRect {
+                left: 0,
+                right: 3,
+                bottom: 0,
+                top: 2
+            }]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn rectangles_cover_exactly_the_cells_without_overlapping((width, height, cells) in grid()) {
+            let rects = merge_cells(width, height, |coords| cells.contains(&coords));
+
+            for x in 0..width {
+                for y in 0..height {
+                    let coords = GridCoords { x, y };
+                    let covering = rects.iter().filter(|rect| rect.contains(coords)).count();
+                    prop_assert_eq!(covering, cells.contains(&coords) as usize, "cell {:?}", coords);
+                }
+            }
+
+            let area: i32 = rects.iter().map(|rect| rect.width() * rect.height()).sum();
+            prop_assert_eq!(area as usize, cells.len());
+        }
+    }
+}
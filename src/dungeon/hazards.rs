@@ -0,0 +1,251 @@
+//! Tiles of the `Hazards` IntGrid layer hurting or hindering whoever stands on them
+//!
+//! Adjacent tiles of the same hazard are merged into sensors the same way walls are,
+//! see [`spawn_merged_colliders`].
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::collisions::{spawn_merged_colliders, MergedCollider};
+use crate::{
+    combat::{hitbox::*, status_effect::*, Attack},
+    layers::Layer,
+    player::Player,
+    stats::{Modifier, ModifierSource, Stat, Stats},
+};
+
+pub(super) const HAZARDS_LAYER_ID: &str = "Hazards";
+const SPIKES_VALUE: i32 = 1;
+const PIT_VALUE: i32 = 2;
+const LAVA_VALUE: i32 = 3;
+const MUD_VALUE: i32 = 4;
+
+pub const SPIKES_DAMAGE: i32 = 5;
+/// Delay between two damages dealt by spikes to an entity standing on them
+pub const SPIKES_INTERVAL: Duration = Duration::from_millis(500);
+/// Burn damage per second
+pub const LAVA_BURN: f32 = 8.;
+/// Duration of the burn after leaving the lava
+pub const LAVA_BURN_DURATION: Duration = Duration::from_secs(2);
+/// Fraction of the movement acceleration removed while in mud
+pub const MUD_SLOW: f32 = 0.5;
+
+pub(super) struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_merged_colliders::<SpikesTile>,
+                spawn_merged_colliders::<PitTile>,
+                spawn_merged_colliders::<LavaTile>,
+                spawn_merged_colliders::<MudTile>,
+                burn_in_lava,
+                slow_in_mud,
+                fall_into_pits,
+            ),
+        )
+        .register_ldtk_int_cell_for_layer::<SpikesTileBundle>(HAZARDS_LAYER_ID, SPIKES_VALUE)
+        .register_ldtk_int_cell_for_layer::<PitTileBundle>(HAZARDS_LAYER_ID, PIT_VALUE)
+        .register_ldtk_int_cell_for_layer::<LavaTileBundle>(HAZARDS_LAYER_ID, LAVA_VALUE)
+        .register_ldtk_int_cell_for_layer::<MudTileBundle>(HAZARDS_LAYER_ID, MUD_VALUE);
+    }
+}
+
+/// Sensor covering adjacent hazard tiles of the same kind
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hazard {
+    /// Damages periodically
+    Spikes,
+    /// The player falls and comes back at its [`SafeSpot`]
+    Pit,
+    /// Sets on fire
+    Lava,
+    /// Slows down
+    Mud,
+}
+
+impl Hazard {
+    fn bundle(self) -> HazardBundle {
+        (
+            self,
+            RigidBody::Static,
+            Sensor,
+            Layer::trigger(),
+            CollidingEntities::default(),
+        )
+    }
+}
+
+/// Components of the sensor of every hazard
+type HazardBundle = (
+    Hazard,
+    RigidBody,
+    Sensor,
+    CollisionLayers,
+    CollidingEntities,
+);
+
+/// Last position of the player outside of any hazard, where it comes back after falling into a pit
+#[derive(Component, Default)]
+pub struct SafeSpot(pub Vec2);
+
+#[derive(Default, Component)]
+pub struct SpikesTile;
+
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct SpikesTileBundle {
+    pub spikes_tile: SpikesTile,
+}
+
+#[derive(Default, Component)]
+pub struct PitTile;
+
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct PitTileBundle {
+    pub pit_tile: PitTile,
+}
+
+#[derive(Default, Component)]
+pub struct LavaTile;
+
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct LavaTileBundle {
+    pub lava_tile: LavaTile,
+}
+
+#[derive(Default, Component)]
+pub struct MudTile;
+
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct MudTileBundle {
+    pub mud_tile: MudTile,
+}
+
+impl MergedCollider for SpikesTile {
+    type Bundle = (HazardBundle, Hitbox, Attack);
+
+    fn bundle() -> Self::Bundle {
+        (
+            Hazard::Spikes.bundle(),
+            Hitbox::new(Team::Enemy).with_rehit_delay(SPIKES_INTERVAL),
+            Attack::new(SPIKES_DAMAGE),
+        )
+    }
+}
+
+impl MergedCollider for PitTile {
+    type Bundle = HazardBundle;
+
+    fn bundle() -> Self::Bundle {
+        Hazard::Pit.bundle()
+    }
+}
+
+impl MergedCollider for LavaTile {
+    type Bundle = HazardBundle;
+
+    fn bundle() -> Self::Bundle {
+        Hazard::Lava.bundle()
+    }
+}
+
+impl MergedCollider for MudTile {
+    type Bundle = HazardBundle;
+
+    fn bundle() -> Self::Bundle {
+        Hazard::Mud.bundle()
+    }
+}
+
+/// Burns the entities in lava, the burn lasts a while after they leave it
+fn burn_in_lava(
+    hazard_query: Query<(Entity, &Hazard, &CollidingEntities)>,
+    mut target_query: Query<(&mut StatusEffects, Option<&Resistances>)>,
+) {
+    for (lava, _, colliding_entities) in hazard_query
+        .iter()
+        .filter(|(_, hazard, _)| **hazard == Hazard::Lava)
+    {
+        for &entity in colliding_entities.iter() {
+            let Ok((mut status_effects, resistances)) = target_query.get_mut(entity) else {
+                continue;
+            };
+
+            let burn = StatusEffect::new(
+                StatusKind::Burn,
+                LAVA_BURN,
+                LAVA_BURN_DURATION,
+                Stacking::Refresh,
+            );
+            let burn = match resistances {
+                Some(resistances) => resistances.resist(burn),
+                None => Some(burn),
+            };
+            if let Some(burn) = burn {
+                status_effects.apply(burn, lava);
+            }
+        }
+    }
+}
+
+/// Scales down the [`Stat::MoveAcceleration`] of the entities in mud
+fn slow_in_mud(
+    hazard_query: Query<(&Hazard, &CollidingEntities)>,
+    mut stats_query: Query<(Entity, &mut Stats)>,
+) {
+    let in_mud: HashSet<Entity> = hazard_query
+        .iter()
+        .filter(|(hazard, _)| **hazard == Hazard::Mud)
+        .flat_map(|(_, colliding_entities)| colliding_entities.iter().copied())
+        .collect();
+
+    for (entity, mut stats) in stats_query.iter_mut() {
+        let modifiers = if in_mud.contains(&entity) {
+            vec![Modifier::multiplicative(Stat::MoveAcceleration, -MUD_SLOW)]
+        } else {
+            Vec::new()
+        };
+        // Only touched when it changes so that stats are not recomputed every frame
+        if stats.modifiers(ModifierSource::Terrain) != modifiers {
+            stats.set_modifiers(ModifierSource::Terrain, modifiers);
+        }
+    }
+}
+
+/// Brings the player back to its [`SafeSpot`] when it falls into a pit,
+/// which happens once its center is over the pit rather than as soon as it touches it
+fn fall_into_pits(
+    hazard_query: Query<(&Hazard, &CollidingEntities, &ColliderAabb)>,
+    mut player_query: Query<
+        (Entity, &mut Position, &mut LinearVelocity, &mut SafeSpot),
+        With<Player>,
+    >,
+) {
+    for (player, mut position, mut linear_velocity, mut safe_spot) in player_query.iter_mut() {
+        let hazards: Vec<(Hazard, &ColliderAabb)> = hazard_query
+            .iter()
+            .filter(|(_, colliding_entities, _)| colliding_entities.contains(&player))
+            .map(|(hazard, _, aabb)| (*hazard, aabb))
+            .collect();
+
+        let over_pit = hazards
+            .iter()
+            .any(|(hazard, aabb)| *hazard == Hazard::Pit && aabb_contains(aabb, position.0));
+        if over_pit {
+            position.0 = safe_spot.0;
+            linear_velocity.0 = Vec2::ZERO;
+        } else if hazards.is_empty() {
+            safe_spot.0 = position.0;
+        }
+    }
+}
+
+fn aabb_contains(aabb: &ColliderAabb, point: Vec2) -> bool {
+    (aabb.0.mins.x..=aabb.0.maxs.x).contains(&point.x)
+        && (aabb.0.mins.y..=aabb.0.maxs.y).contains(&point.y)
+}
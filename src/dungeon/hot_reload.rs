@@ -0,0 +1,54 @@
+//! Respawning the levels without resetting the player when the LDtk project changes on disk
+//!
+//! The project is only watched when the game is built with the `hot_reload` feature.
+//! `bevy_ecs_ldtk` respawns the whole world when the project is modified. The player is moved
+//! out of the world beforehand so that it survives with its position, stats and inventory,
+//! and the player spawned by the new world is removed.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::player::Player;
+
+pub(super) struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        // The world respawns during `PreUpdate`
+        app.add_systems(First, detach_player_on_map_change)
+            .add_systems(PostUpdate, despawn_respawned_players);
+    }
+}
+
+fn detach_player_on_map_change(
+    mut commands: Commands,
+    mut ldtk_project_events: EventReader<AssetEvent<LdtkProject>>,
+    player_query: Query<Entity, (With<Player>, With<Parent>)>,
+) {
+    if !ldtk_project_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }))
+    {
+        return;
+    }
+
+    for player in player_query.iter() {
+        info!("map modified, keeping the player while the levels respawn");
+        commands.entity(player).remove_parent_in_place();
+    }
+}
+
+/// Keeps the player that was there before the map changed
+fn despawn_respawned_players(
+    mut commands: Commands,
+    added_query: Query<Entity, Added<Player>>,
+    player_query: Query<(), With<Player>>,
+) {
+    if player_query.iter().count() < 2 {
+        return;
+    }
+
+    for player in added_query.iter() {
+        commands.entity(player).despawn_recursive();
+    }
+}
@@ -0,0 +1,335 @@
+//! World bounds of every level of the project, indexed for fast point and area lookups
+//!
+//! The bounds are read from the [`LdtkProject`] once it loads, so levels that are not spawned
+//! yet can be found too, then follow the levels as they spawn.
+
+use std::ops::ControlFlow;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+
+pub(super) struct LevelBoundsPlugin;
+
+impl Plugin for LevelBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelBounds>().add_systems(
+            Update,
+            (index_project_levels, index_spawned_levels)
+                .chain()
+                .in_set(LevelBoundsSet),
+        );
+    }
+}
+
+/// Systems keeping [`LevelBounds`] up to date, systems reading it should run after them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LevelBoundsSet;
+
+/// Bounding volume hierarchy over the world bounds of the levels
+///
+/// Levels are split in halves along the longest axis at every node,
+/// so lookups only visit the branches around the searched point or area.
+#[derive(Resource, Default, Debug)]
+pub struct LevelBounds {
+    levels: Vec<(LevelIid, Rect)>,
+    indices: HashMap<LevelIid, usize>,
+    /// Nodes of the tree, the root is the first one
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Branch {
+        bounds: Rect,
+        left: usize,
+        right: usize,
+    },
+    Leaf {
+        level: usize,
+    },
+}
+
+impl LevelBounds {
+    pub fn new(levels: impl IntoIterator<Item = (LevelIid, Rect)>) -> Self {
+        let mut level_bounds = LevelBounds::default();
+        level_bounds.extend(levels);
+        level_bounds
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn get(&self, level_iid: &LevelIid) -> Option<Rect> {
+        self.indices
+            .get(level_iid)
+            .map(|&index| self.levels[index].1)
+    }
+
+    /// Adds levels or moves the ones already indexed, then rebuilds the tree once
+    pub fn extend(&mut self, levels: impl IntoIterator<Item = (LevelIid, Rect)>) {
+        for (level_iid, bounds) in levels {
+            match self.indices.get(&level_iid) {
+                Some(&index) => self.levels[index].1 = bounds,
+                None => {
+                    self.indices.insert(level_iid.clone(), self.levels.len());
+                    self.levels.push((level_iid, bounds));
+                }
+            }
+        }
+
+        self.nodes.clear();
+        if !self.levels.is_empty() {
+            let mut order: Vec<usize> = (0..self.levels.len()).collect();
+            self.build(&mut order);
+        }
+    }
+
+    /// Appends the subtree of the levels at `order` and returns the index of its root
+    fn build(&mut self, order: &mut [usize]) -> usize {
+        let node = self.nodes.len();
+        if let [level] = order {
+            self.nodes.push(Node::Leaf { level: *level });
+            return node;
+        }
+
+        let bounds = order
+            .iter()
+            .map(|&level| self.levels[level].1)
+            .reduce(|a, b| a.union(b))
+            .expect("subtrees should not be empty");
+        let axis = if bounds.width() >= bounds.height() {
+            0
+        } else {
+            1
+        };
+        order.sort_by(|&a, &b| {
+            let a = self.levels[a].1.center()[axis];
+            let b = self.levels[b].1.center()[axis];
+            a.total_cmp(&b)
+        });
+
+        // Placeholder until the children know their index
+        self.nodes.push(Node::Leaf { level: 0 });
+        let (left_order, right_order) = order.split_at_mut(order.len() / 2);
+        let left = self.build(left_order);
+        let right = self.build(right_order);
+        self.nodes[node] = Node::Branch {
+            bounds,
+            left,
+            right,
+        };
+        node
+    }
+
+    /// Visits the levels whose bounds may pass `filter`, skipping the branches that do not,
+    /// until `visitor` breaks with a value
+    fn visit<'a, B>(
+        &'a self,
+        filter: impl Fn(Rect) -> bool,
+        mut visitor: impl FnMut(&'a LevelIid, Rect) -> ControlFlow<B>,
+    ) -> Option<B> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        match self.visit_node(0, &filter, &mut visitor) {
+            ControlFlow::Break(value) => Some(value),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    fn visit_node<'a, B>(
+        &'a self,
+        node: usize,
+        filter: &impl Fn(Rect) -> bool,
+        visitor: &mut impl FnMut(&'a LevelIid, Rect) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        match self.nodes[node] {
+            Node::Branch {
+                bounds,
+                left,
+                right,
+            } => {
+                if filter(bounds) {
+                    self.visit_node(left, filter, visitor)?;
+                    self.visit_node(right, filter, visitor)?;
+                }
+                ControlFlow::Continue(())
+            }
+            Node::Leaf { level } => {
+                let (level_iid, bounds) = &self.levels[level];
+                if filter(*bounds) {
+                    visitor(level_iid, *bounds)
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        }
+    }
+
+    /// Level containing `point`, levels sharing an edge both contain the points on it
+    pub fn level_at(&self, point: Vec2) -> Option<(&LevelIid, Rect)> {
+        self.visit(
+            |bounds| bounds.contains(point),
+            |level_iid, bounds| ControlFlow::Break((level_iid, bounds)),
+        )
+    }
+
+    /// Levels containing `point`
+    pub fn levels_at(&self, point: Vec2) -> Vec<(&LevelIid, Rect)> {
+        let mut levels = Vec::new();
+        self.visit(
+            |bounds| bounds.contains(point),
+            |level_iid, bounds| {
+                levels.push((level_iid, bounds));
+                ControlFlow::<()>::Continue(())
+            },
+        );
+        levels
+    }
+
+    /// Levels overlapping `area`, such as the ones visible to a camera
+    pub fn levels_in(&self, area: Rect) -> Vec<(&LevelIid, Rect)> {
+        let mut levels = Vec::new();
+        self.visit(
+            |bounds| !bounds.intersect(area).is_empty(),
+            |level_iid, bounds| {
+                levels.push((level_iid, bounds));
+                ControlFlow::<()>::Continue(())
+            },
+        );
+        levels
+    }
+}
+
+/// World bounds of a level spawned with [`LevelSpawnBehavior::UseWorldTranslation`]
+fn raw_level_bounds(level: &Level) -> Rect {
+    let min = Vec2::new(level.world_x as f32, -(level.world_y + level.px_hei) as f32);
+    Rect::from_corners(
+        min,
+        min + Vec2::new(level.px_wid as f32, level.px_hei as f32),
+    )
+}
+
+/// Indexes every level of the project when it loads or changes on disk
+fn index_project_levels(
+    mut asset_event_reader: EventReader<AssetEvent<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    mut level_bounds: ResMut<LevelBounds>,
+) {
+    for event in asset_event_reader.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if !ldtk_projects.iter().any(|handle| handle.id() == *id) {
+            continue;
+        }
+        let Some(ldtk_project) = ldtk_project_assets.get(*id) else {
+            continue;
+        };
+
+        *level_bounds = LevelBounds::new(
+            ldtk_project
+                .iter_raw_levels()
+                .map(|level| (LevelIid::new(level.iid.clone()), raw_level_bounds(level))),
+        );
+    }
+}
+
+/// Moves the bounds of the levels to where they actually spawned
+fn index_spawned_levels(
+    level_query: Query<(&LevelIid, &GlobalTransform), Changed<GlobalTransform>>,
+    mut level_bounds: ResMut<LevelBounds>,
+) {
+    let moved: Vec<(LevelIid, Rect)> = level_query
+        .iter()
+        .filter_map(|(level_iid, transform)| {
+            let bounds = level_bounds.get(level_iid)?;
+            let min = transform.translation().truncate();
+            (bounds.min != min).then(|| {
+                (
+                    level_iid.clone(),
+                    Rect::from_corners(min, min + bounds.size()),
+                )
+            })
+        })
+        .collect();
+
+    if !moved.is_empty() {
+        level_bounds.extend(moved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOM_SIZE: Vec2 = Vec2::new(560., 304.);
+
+    /// Rooms of a `size` x `size` grid, named after their coordinates
+    fn grid(size: i32) -> LevelBounds {
+        LevelBounds::new((0..size).flat_map(|x| {
+            (0..size).map(move |y| {
+                let min = Vec2::new(x as f32, y as f32) * ROOM_SIZE;
+                (
+                    LevelIid::new(format!("{x},{y}")),
+                    Rect::from_corners(min, min + ROOM_SIZE),
+                )
+            })
+        }))
+    }
+
+    #[test]
+    fn finds_the_level_containing_a_point() {
+        let level_bounds = grid(12);
+
+        for (x, y) in [(0, 0), (11, 11), (3, 7), (8, 2)] {
+            let point = (Vec2::new(x as f32, y as f32) + 0.5) * ROOM_SIZE;
+            let (level_iid, bounds) = level_bounds.level_at(point).unwrap();
+            assert_eq!(*level_iid, LevelIid::new(format!("{x},{y}")));
+            assert!(bounds.contains(point));
+        }
+        assert_eq!(level_bounds.level_at(Vec2::new(-1., 10.)), None);
+    }
+
+    #[test]
+    fn finds_the_levels_overlapping_an_area() {
+        let level_bounds = grid(12);
+        let area = Rect::from_corners(ROOM_SIZE * 2.5, ROOM_SIZE * 4.5);
+
+        let mut levels: Vec<String> = level_bounds
+            .levels_in(area)
+            .into_iter()
+            .map(|(level_iid, _)| level_iid.get().clone())
+            .collect();
+        levels.sort();
+
+        assert_eq!(
+            levels,
+            ["2,2", "2,3", "2,4", "3,2", "3,3", "3,4", "4,2", "4,3", "4,4"]
+        );
+    }
+
+    #[test]
+    fn moved_levels_are_found_at_their_new_position() {
+        let mut level_bounds = grid(3);
+        let level_iid = LevelIid::new("1,1");
+        let moved = Rect::from_corners(Vec2::new(-2000., 0.), Vec2::new(-2000., 0.) + ROOM_SIZE);
+
+        level_bounds.extend([(level_iid.clone(), moved)]);
+
+        assert_eq!(level_bounds.len(), 9);
+        assert_eq!(level_bounds.get(&level_iid), Some(moved));
+        assert_eq!(
+            level_bounds.level_at(moved.center()),
+            Some((&level_iid, moved))
+        );
+        assert_eq!(level_bounds.level_at(ROOM_SIZE * 1.5), None);
+    }
+}
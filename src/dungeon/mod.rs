@@ -2,28 +2,64 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
 use collisions::*;
+use hazards::*;
+use hot_reload::*;
+use level_bounds::*;
+use rooms::*;
 
 pub mod collisions;
-pub struct DungeonPlugin;
+pub mod grid_rects;
+pub mod hazards;
+mod hot_reload;
+pub mod level_bounds;
+pub mod rooms;
+pub mod slopes;
+pub mod validation;
+
+pub const MAP_PATH: &str = "map.ldtk";
+
+pub struct DungeonPlugin {
+    /// LDtk project to load, relative to the assets folder
+    pub map: String,
+}
+
+impl Default for DungeonPlugin {
+    fn default() -> Self {
+        Self {
+            map: MAP_PATH.into(),
+        }
+    }
+}
+
+/// LDtk project loaded by [`setup_ldtk`]
+#[derive(Resource)]
+struct DungeonMap(String);
 
 impl Plugin for DungeonPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(CollisionsPlugin)
-            .add_systems(Startup, setup_ldtk)
-            .insert_resource(LevelSelection::index(0))
-            .insert_resource(LdtkSettings {
-                set_clear_color: SetClearColor::FromLevelBackground,
-                level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
-                    load_level_neighbors: true,
-                },
-                ..Default::default()
-            });
+        app.add_plugins((
+            CollisionsPlugin,
+            HazardsPlugin,
+            HotReloadPlugin,
+            LevelBoundsPlugin,
+            RoomsPlugin,
+        ))
+        .insert_resource(DungeonMap(self.map.clone()))
+        .add_systems(Startup, setup_ldtk)
+        .insert_resource(LevelSelection::index(0))
+        .insert_resource(LdtkSettings {
+            set_clear_color: SetClearColor::FromLevelBackground,
+            level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
+                load_level_neighbors: true,
+            },
+            ..Default::default()
+        });
     }
 }
 
-fn setup_ldtk(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_ldtk(mut commands: Commands, asset_server: Res<AssetServer>, map: Res<DungeonMap>) {
     commands.spawn(LdtkWorldBundle {
-        ldtk_handle: asset_server.load("map.ldtk"),
+        ldtk_handle: asset_server.load(&map.0),
         ..Default::default()
     });
 }
@@ -0,0 +1,95 @@
+//! Tracks the level the player is in and announces when it changes
+//!
+//! Systems reacting to the player changing room, such as door locks, music or spawners,
+//! read [`RoomEntered`] and [`RoomExited`] instead of watching [`LevelSelection`].
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use super::level_bounds::{LevelBounds, LevelBoundsSet};
+use crate::player::Player;
+
+pub(super) struct RoomsPlugin;
+
+impl Plugin for RoomsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentRoom>()
+            .add_event::<RoomEntered>()
+            .add_event::<RoomExited>()
+            .add_systems(
+                Update,
+                (track_player_room, select_entered_room)
+                    .chain()
+                    .after(LevelBoundsSet),
+            );
+    }
+}
+
+/// An event sent when the player enters the level `level_iid`
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct RoomEntered {
+    pub level_iid: LevelIid,
+}
+
+/// An event sent when the player leaves the level `level_iid`, before the matching [`RoomEntered`]
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct RoomExited {
+    pub level_iid: LevelIid,
+}
+
+/// Level the player is in, `None` until it is first found in one
+#[derive(Resource, Default, Debug)]
+pub struct CurrentRoom {
+    level_iid: Option<LevelIid>,
+    bounds: Rect,
+}
+
+impl CurrentRoom {
+    pub fn level_iid(&self) -> Option<&LevelIid> {
+        self.level_iid.as_ref()
+    }
+
+    /// World space bounds of the level
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+}
+
+/// Looks for the level containing the player once it has left the current one
+fn track_player_room(
+    player_query: Query<&GlobalTransform, (With<Player>, Changed<GlobalTransform>)>,
+    level_bounds: Res<LevelBounds>,
+    mut current_room: ResMut<CurrentRoom>,
+    mut room_exited_writer: EventWriter<RoomExited>,
+    mut room_entered_writer: EventWriter<RoomEntered>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let position = player_transform.translation().truncate();
+    if current_room.level_iid.is_some() && current_room.bounds.contains(position) {
+        return;
+    }
+    // Between two levels the player still belongs to the one it came from
+    let Some((level_iid, bounds)) = level_bounds.level_at(position) else {
+        return;
+    };
+
+    if let Some(exited) = current_room.level_iid.replace(level_iid.clone()) {
+        room_exited_writer.send(RoomExited { level_iid: exited });
+    }
+    current_room.bounds = bounds;
+    room_entered_writer.send(RoomEntered {
+        level_iid: level_iid.clone(),
+    });
+}
+
+/// Loads the level the player entered and its neighbors
+fn select_entered_room(
+    mut room_entered_reader: EventReader<RoomEntered>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    if let Some(event) = room_entered_reader.read().last() {
+        *level_selection = LevelSelection::Iid(event.level_iid.clone());
+    }
+}
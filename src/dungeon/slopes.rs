@@ -0,0 +1,237 @@
+use bevy::{
+    math::Vec2,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_ldtk::GridCoords;
+
+use super::grid_rects::GridRect;
+
+/// A 45° wall filling half of a cell, named after its solid corner
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Slope {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+impl Slope {
+    /// Step from a cell of a diagonal wall to the next one, from left to right
+    fn step(self) -> GridCoords {
+        match self {
+            Slope::BottomLeft | Slope::TopRight => GridCoords { x: 1, y: -1 },
+            Slope::BottomRight | Slope::TopLeft => GridCoords { x: 1, y: 1 },
+        }
+    }
+
+    /// Whether the cell at `offset` from a cell of the diagonal is on the solid side of it
+    fn is_behind(self, offset: GridCoords) -> bool {
+        match self {
+            Slope::BottomLeft => offset.x + offset.y < 0,
+            Slope::TopRight => offset.x + offset.y > 0,
+            Slope::BottomRight => offset.x - offset.y > 0,
+            Slope::TopLeft => offset.x - offset.y < 0,
+        }
+    }
+}
+
+/// Right triangle made of a diagonal of slope cells and the wall cells behind it
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct SlopeRun {
+    pub slope: Slope,
+    /// Square of cells covered by the triangle
+    pub rect: GridRect,
+}
+
+impl SlopeRun {
+    /// Corners of the triangle in cells, relative to the center of its rectangle
+    pub fn vertices(&self) -> [Vec2; 3] {
+        let half = self.rect.width() as f32 / 2.;
+        let (left, right, bottom, top) = (-half, half, -half, half);
+        match self.slope {
+            Slope::BottomLeft => [
+                Vec2::new(left, bottom),
+                Vec2::new(right, bottom),
+                Vec2::new(left, top),
+            ],
+            Slope::BottomRight => [
+                Vec2::new(left, bottom),
+                Vec2::new(right, bottom),
+                Vec2::new(right, top),
+            ],
+            Slope::TopLeft => [
+                Vec2::new(left, bottom),
+                Vec2::new(right, top),
+                Vec2::new(left, top),
+            ],
+            Slope::TopRight => [
+                Vec2::new(right, bottom),
+                Vec2::new(right, top),
+                Vec2::new(left, top),
+            ],
+        }
+    }
+}
+
+/// Merges diagonals of slope cells of the same kind into triangles
+///
+/// A slope cell only extends the diagonal before it when all the cells between the
+/// diagonal and the solid corner of the resulting triangle are walls,
+/// so the triangles cover exactly the slope cells and some walls.
+/// Long diagonal walls then have a single smooth face for characters to slide along,
+/// instead of a sawtooth of small triangles.
+pub fn merge_slopes(
+    slopes: &HashMap<GridCoords, Slope>,
+    is_wall: impl Fn(GridCoords) -> bool,
+) -> Vec<SlopeRun> {
+    let mut cells: Vec<(GridCoords, Slope)> = slopes
+        .iter()
+        .map(|(&coords, &slope)| (coords, slope))
+        .collect();
+    // Diagonals are walked from left to right
+    cells.sort_by_key(|(coords, _)| (coords.x, coords.y));
+
+    let mut merged: HashSet<GridCoords> = HashSet::new();
+    let mut runs = Vec::new();
+
+    for (start, slope) in cells {
+        if !merged.insert(start) {
+            continue;
+        }
+
+        let step = slope.step();
+        let along = |distance: i32| GridCoords {
+            x: start.x + step.x * distance,
+            y: start.y + step.y * distance,
+        };
+        let mut length = 1;
+        loop {
+            let next = along(length);
+            if slopes.get(&next) != Some(&slope) || merged.contains(&next) {
+                break;
+            }
+            // The grown triangle adds the row and the column of the new cell
+            let behind_is_wall = (0..length).all(|distance| {
+                let in_row = GridCoords {
+                    x: along(distance).x,
+                    y: next.y,
+                };
+                let in_column = GridCoords {
+                    x: next.x,
+                    y: along(distance).y,
+                };
+                [in_row, in_column].into_iter().all(|cell| {
+                    let offset = GridCoords {
+                        x: cell.x - start.x,
+                        y: cell.y - start.y,
+                    };
+                    !slope.is_behind(offset) || is_wall(cell)
+                })
+            });
+            if !behind_is_wall {
+                break;
+            }
+            merged.insert(next);
+            length += 1;
+        }
+
+        let end = along(length - 1);
+        runs.push(SlopeRun {
+            slope,
+            rect: GridRect {
+                left: start.x,
+                right: end.x,
+                bottom: start.y.min(end.y),
+                top: start.y.max(end.y),
+            },
+        });
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coords(x: i32, y: i32) -> GridCoords {
+        GridCoords { x, y }
+    }
+
+    /// Slopes of the same kind on a diagonal, from left to right
+    fn diagonal(slope: Slope, start: GridCoords, length: i32) -> HashMap<GridCoords, Slope> {
+        let step = slope.step();
+        (0..length)
+            .map(|i| (coords(start.x + step.x * i, start.y + step.y * i), slope))
+            .collect()
+    }
+
+    #[test]
+    fn merges_a_diagonal_backed_by_walls_into_one_triangle() {
+        // Walls fill the bottom left corner below the diagonal from (0, 3) to (3, 0)
+        let slopes = diagonal(Slope::BottomLeft, coords(0, 3), 4);
+        let runs = merge_slopes(&slopes, |cell| cell.x + cell.y < 3);
+
+        assert_eq!(
+            runs,
+            vec![SlopeRun {
+                slope: Slope::BottomLeft,
+                rect: GridRect {
+                    left: 0,
+                    right: 3,
+                    bottom: 0,
+                    top: 3
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn splits_diagonals_where_the_cells_behind_are_not_walls() {
+        let slopes = diagonal(Slope::BottomRight, coords(0, 0), 3);
+        let runs = merge_slopes(&slopes, |_| false);
+
+        assert_eq!(runs.len(), 3);
+        assert!(runs
+            .iter()
+            .all(|run| run.rect.width() == 1 && run.rect.height() == 1));
+    }
+
+    #[test]
+    fn does_not_merge_different_slopes() {
+        let mut slopes = diagonal(Slope::TopLeft, coords(0, 0), 2);
+        slopes.extend(diagonal(Slope::TopRight, coords(2, 2), 2));
+        let runs = merge_slopes(&slopes, |_| true);
+
+        let mut kinds: Vec<(Slope, i32)> = runs
+            .iter()
+            .map(|run| (run.slope, run.rect.width()))
+            .collect();
+        kinds.sort_by_key(|(_, width)| *width);
+        assert_eq!(kinds, vec![(Slope::TopLeft, 2), (Slope::TopRight, 2)]);
+    }
+
+    #[test]
+    fn triangles_have_their_right_angle_at_the_solid_corner() {
+        let run = |slope| SlopeRun {
+            slope,
+            rect: GridRect {
+                left: 0,
+                right: 1,
+                bottom: 0,
+                top: 1,
+            },
+        };
+
+        assert!(run(Slope::BottomLeft)
+            .vertices()
+            .contains(&Vec2::new(-1., -1.)));
+        assert!(!run(Slope::BottomLeft)
+            .vertices()
+            .contains(&Vec2::new(1., 1.)));
+        assert!(run(Slope::TopRight).vertices().contains(&Vec2::new(1., 1.)));
+        assert!(!run(Slope::TopRight)
+            .vertices()
+            .contains(&Vec2::new(-1., -1.)));
+    }
+}
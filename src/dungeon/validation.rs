@@ -0,0 +1,441 @@
+//! Checks of an LDtk map for the mistakes that break the game, run by the `validate-map` binary
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::ldtk::{EntityInstance, LdtkJson, Level};
+use thiserror::Error;
+
+use super::collisions::{COLLIDER_VALUE, COLLISIONS_LAYER_ID, DOOR_VALUE, SLOPE_VALUES};
+use crate::{
+    boss::Boss,
+    ldtk_fields::{EntityFields, FieldError, FromFields},
+    mob::MobFields,
+};
+
+const PLAYER_ENTITY: &str = "Player";
+const MOB_ENTITY: &str = "Mob";
+const BOSS_ENTITY: &str = "Boss";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MapError {
+    #[error("the map should have exactly one {PLAYER_ENTITY} entity, it has {0}")]
+    PlayerCount(usize),
+    #[error("level {level}: no {COLLISIONS_LAYER_ID} layer")]
+    MissingCollisionLayer { level: String },
+    #[error("level {level}, entity {entity} {iid}: overlaps the wall at {cell}")]
+    EntityInWall {
+        level: String,
+        entity: String,
+        iid: String,
+        cell: IVec2,
+    },
+    #[error("level {level}: the door at {cell} does not line up with a door of another level")]
+    MisalignedDoor { level: String, cell: IVec2 },
+    #[error("level {level}: no door leads to it from the level of the player")]
+    UnreachableLevel { level: String },
+    #[error(transparent)]
+    Field(#[from] FieldError),
+}
+
+/// Every mistake found in the map, the map is valid if there are none
+pub fn validate(project: &LdtkJson) -> Vec<MapError> {
+    let mut errors = Vec::new();
+
+    let player_levels: Vec<usize> = project
+        .levels
+        .iter()
+        .enumerate()
+        .flat_map(|(index, level)| {
+            entities(level)
+                .filter(|entity| entity.identifier == PLAYER_ENTITY)
+                .map(move |_| index)
+        })
+        .collect();
+    if player_levels.len() != 1 {
+        errors.push(MapError::PlayerCount(player_levels.len()));
+    }
+
+    let grids: Vec<Option<CollisionGrid>> = project.levels.iter().map(CollisionGrid::new).collect();
+    for (level, grid) in project.levels.iter().zip(&grids) {
+        match grid {
+            Some(grid) => check_walls(level, grid, &mut errors),
+            None => errors.push(MapError::MissingCollisionLayer {
+                level: level.identifier.clone(),
+            }),
+        }
+        check_fields(project, level, &mut errors);
+    }
+
+    let links = link_doors(&project.levels, &grids, &mut errors);
+    // Reachability is only meaningful from a single player
+    if let [start] = player_levels[..] {
+        check_reachability(&project.levels, &links, start, &mut errors);
+    }
+
+    errors
+}
+
+fn entities(level: &Level) -> impl Iterator<Item = &EntityInstance> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| &layer.entity_instances)
+}
+
+/// Cells of the collisions layer of a level, `y` points down as in LDtk
+struct CollisionGrid<'a> {
+    /// World position of the top left corner of the level, in pixels
+    origin: IVec2,
+    size: IVec2,
+    cell_size: i32,
+    values: &'a [i32],
+}
+
+impl<'a> CollisionGrid<'a> {
+    fn new(level: &'a Level) -> Option<Self> {
+        let layer = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .find(|layer| layer.identifier == COLLISIONS_LAYER_ID)?;
+
+        Some(CollisionGrid {
+            origin: IVec2::new(level.world_x, level.world_y),
+            size: IVec2::new(layer.c_wid, layer.c_hei),
+            cell_size: layer.grid_size,
+            values: &layer.int_grid_csv,
+        })
+    }
+
+    fn contains(&self, cell: IVec2) -> bool {
+        cell.cmpge(IVec2::ZERO).all() && cell.cmplt(self.size).all()
+    }
+
+    /// Value of the cell, 0 outside of the grid
+    fn get(&self, cell: IVec2) -> i32 {
+        if !self.contains(cell) {
+            return 0;
+        }
+        self.values
+            .get((cell.y * self.size.x + cell.x) as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.size.y).flat_map(move |y| (0..self.size.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    /// Cell at a world position in pixels, if it is in the grid
+    fn cell_at(&self, position: IVec2) -> Option<IVec2> {
+        let cell = (position - self.origin).div_euclid(IVec2::splat(self.cell_size));
+        self.contains(cell).then_some(cell)
+    }
+
+    /// World position of the center of a cell, in pixels
+    fn center(&self, cell: IVec2) -> IVec2 {
+        self.origin + cell * self.cell_size + self.cell_size / 2
+    }
+
+    /// Directions toward the outside of the level from a cell on its border
+    fn outward(&self, cell: IVec2) -> Vec<IVec2> {
+        [
+            (cell.x == 0, IVec2::NEG_X),
+            (cell.x == self.size.x - 1, IVec2::X),
+            (cell.y == 0, IVec2::NEG_Y),
+            (cell.y == self.size.y - 1, IVec2::Y),
+        ]
+        .into_iter()
+        .filter_map(|(on_border, direction)| on_border.then_some(direction))
+        .collect()
+    }
+}
+
+/// Whether a value of the collisions layer is a full or 45° wall
+fn is_wall(value: i32) -> bool {
+    value == COLLIDER_VALUE || SLOPE_VALUES.iter().any(|&(slope, _)| slope == value)
+}
+
+/// Reports the entities overlapping a wall cell, slopes included
+fn check_walls(level: &Level, grid: &CollisionGrid, errors: &mut Vec<MapError>) {
+    for entity in entities(level) {
+        let size = IVec2::new(entity.width, entity.height);
+        let top_left = entity.px - (entity.pivot * size.as_vec2()).round().as_ivec2();
+        let first = top_left.div_euclid(IVec2::splat(grid.cell_size));
+        let last = (top_left + size - 1).div_euclid(IVec2::splat(grid.cell_size));
+
+        let wall = (first.y..=last.y)
+            .flat_map(|y| (first.x..=last.x).map(move |x| IVec2::new(x, y)))
+            .find(|&cell| is_wall(grid.get(cell)));
+        if let Some(cell) = wall {
+            errors.push(MapError::EntityInWall {
+                level: level.identifier.clone(),
+                entity: entity.identifier.clone(),
+                iid: entity.iid.clone(),
+                cell,
+            });
+        }
+    }
+}
+
+/// Fields that cannot be null in LDtk must be set, the fields of mobs and bosses must be readable
+fn check_fields(project: &LdtkJson, level: &Level, errors: &mut Vec<MapError>) {
+    for entity in entities(level) {
+        let fields = EntityFields::from_entity_instance(entity, &level.identifier);
+        let definition = project
+            .defs
+            .entities
+            .iter()
+            .find(|definition| definition.identifier == entity.identifier);
+
+        let missing: Vec<FieldError> = definition
+            .into_iter()
+            .flat_map(|definition| &definition.field_defs)
+            .filter(|field| !field.can_be_null)
+            .filter_map(|field| fields.require(&field.identifier).err())
+            .collect();
+
+        if !missing.is_empty() {
+            errors.extend(missing.into_iter().map(MapError::Field));
+            continue;
+        }
+        let read = match entity.identifier.as_str() {
+            MOB_ENTITY => MobFields::from_fields(&fields).err(),
+            BOSS_ENTITY => Boss::from_fields(&fields).err(),
+            _ => None,
+        };
+        errors.extend(read.map(MapError::Field));
+    }
+}
+
+/// Reports the levels that cannot be reached through doors from the level `start`
+fn check_reachability(
+    levels: &[Level],
+    links: &[(usize, usize)],
+    start: usize,
+    errors: &mut Vec<MapError>,
+) {
+    let mut reached = HashSet::from_iter([start]);
+    let mut to_visit = vec![start];
+    while let Some(index) = to_visit.pop() {
+        for &(from, to) in links {
+            let next = if from == index {
+                to
+            } else if to == index {
+                from
+            } else {
+                continue;
+            };
+            if reached.insert(next) {
+                to_visit.push(next);
+            }
+        }
+    }
+    for (index, level) in levels.iter().enumerate() {
+        if !reached.contains(&index) {
+            errors.push(MapError::UnreachableLevel {
+                level: level.identifier.clone(),
+            });
+        }
+    }
+}
+
+/// Pairs of levels connected by a door, doors connect to a door cell right across the border
+fn link_doors(
+    levels: &[Level],
+    grids: &[Option<CollisionGrid>],
+    errors: &mut Vec<MapError>,
+) -> Vec<(usize, usize)> {
+    let mut links = Vec::new();
+
+    for (index, grid) in grids.iter().enumerate() {
+        let Some(grid) = grid else {
+            continue;
+        };
+        let mut misaligned = Vec::new();
+
+        for cell in grid.cells().filter(|&cell| grid.get(cell) == DOOR_VALUE) {
+            let across: Vec<usize> = grid
+                .outward(cell)
+                .into_iter()
+                .map(|direction| grid.center(cell + direction))
+                .flat_map(|position| {
+                    grids.iter().enumerate().filter_map(move |(other, grid)| {
+                        let grid = grid.as_ref()?;
+                        let cell = grid.cell_at(position)?;
+                        (other != index && grid.get(cell) == DOOR_VALUE).then_some(other)
+                    })
+                })
+                .collect();
+
+            if across.is_empty() {
+                misaligned.push(cell);
+            }
+            for other in across {
+                if !links.contains(&(index, other)) && !links.contains(&(other, index)) {
+                    links.push((index, other));
+                }
+            }
+        }
+
+        while !misaligned.is_empty() {
+            // Adjacent cells belong to the same door, which is reported once
+            let first = misaligned.remove(0);
+            let mut door = vec![first];
+            while let Some(cell) = door.pop() {
+                let (adjacent, rest): (Vec<IVec2>, Vec<IVec2>) = misaligned
+                    .into_iter()
+                    .partition(|other| (*other - cell).abs().element_sum() == 1);
+                misaligned = rest;
+                door.extend(adjacent);
+            }
+            errors.push(MapError::MisalignedDoor {
+                level: levels[index].identifier.clone(),
+                cell: first,
+            });
+        }
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::FieldValue;
+
+    use super::*;
+    use crate::ldtk_fields::FieldErrorKind;
+
+    fn load(path: &str) -> LdtkJson {
+        let content = std::fs::read_to_string(format!("assets/{path}")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    fn entity_mut<'a>(project: &'a mut LdtkJson, identifier: &str) -> &'a mut EntityInstance {
+        project
+            .levels
+            .iter_mut()
+            .flat_map(|level| level.layer_instances.iter_mut().flatten())
+            .flat_map(|layer| &mut layer.entity_instances)
+            .find(|entity| entity.identifier == identifier)
+            .unwrap()
+    }
+
+    #[test]
+    fn bundled_map_is_valid() {
+        assert_eq!(validate(&load(super::super::MAP_PATH)), Vec::new());
+    }
+
+    #[test]
+    fn doors_leading_nowhere_are_reported_once() {
+        let errors = validate(&load("tests/room_0.ldtk"));
+
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(matches!(errors[0], MapError::MisalignedDoor { .. }));
+    }
+
+    #[test]
+    fn maps_need_a_single_player() {
+        let mut project = load(super::super::MAP_PATH);
+        let player = entity_mut(&mut project, PLAYER_ENTITY).clone();
+        project.levels[1].layer_instances.as_mut().unwrap()[2]
+            .entity_instances
+            .push(player);
+
+        assert!(validate(&project).contains(&MapError::PlayerCount(2)));
+    }
+
+    #[test]
+    fn reachability_is_not_checked_without_a_single_player() {
+        let mut project = load(super::super::MAP_PATH);
+        for level in &mut project.levels {
+            for layer in level.layer_instances.iter_mut().flatten() {
+                layer
+                    .entity_instances
+                    .retain(|entity| entity.identifier != PLAYER_ENTITY);
+            }
+        }
+
+        assert_eq!(validate(&project), vec![MapError::PlayerCount(0)]);
+    }
+
+    #[test]
+    fn entities_in_walls_are_reported() {
+        let mut project = load(super::super::MAP_PATH);
+        // The second row of Room_0 is a wall, the pivot of mobs is at their bottom center
+        entity_mut(&mut project, MOB_ENTITY).px = IVec2::new(8, 32);
+
+        let errors = validate(&project);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                MapError::EntityInWall { cell, .. } if *cell == IVec2::new(0, 1)
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn entities_in_slopes_are_reported() {
+        let mut project = load(super::super::MAP_PATH);
+        entity_mut(&mut project, MOB_ENTITY).px = IVec2::new(8, 32);
+        let collisions = project.levels[0]
+            .layer_instances
+            .iter_mut()
+            .flatten()
+            .find(|layer| layer.identifier == COLLISIONS_LAYER_ID)
+            .unwrap();
+        // The first cell of the second row becomes a slope
+        collisions.int_grid_csv[collisions.c_wid as usize] = SLOPE_VALUES[0].0;
+
+        let errors = validate(&project);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                MapError::EntityInWall { cell, .. } if *cell == IVec2::new(0, 1)
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_reported() {
+        let mut project = load(super::super::MAP_PATH);
+        entity_mut(&mut project, MOB_ENTITY)
+            .field_instances
+            .retain(|field| field.identifier != "Kind");
+
+        let errors = validate(&project);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                MapError::Field(FieldError { field, .. }) if field == "Kind"
+            )),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn boss_fields_are_reported() {
+        let mut project = load(super::super::MAP_PATH);
+        let definition = entity_mut(&mut project, BOSS_ENTITY)
+            .field_instances
+            .iter_mut()
+            .find(|field| field.identifier == "Definition")
+            .unwrap();
+        definition.value = FieldValue::Int(Some(1));
+
+        let errors = validate(&project);
+        assert!(
+            errors.iter().any(|error| matches!(
+                error,
+                MapError::Field(FieldError {
+                    field,
+                    kind: FieldErrorKind::WrongType { .. },
+                    ..
+                }) if field == "Definition"
+            )),
+            "{errors:?}"
+        );
+    }
+}
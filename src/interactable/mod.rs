@@ -0,0 +1,88 @@
+//! Objects of the levels the player uses or breaks, wired together by signals
+
+use bevy::prelude::*;
+
+use crate::{inventory::ui::inventory_open, player::Player, run::RunState};
+
+use self::{props::*, signal::*};
+
+pub mod props;
+pub mod signal;
+
+/// Maximum distance between the player and an [`Interactable`] it uses
+pub const INTERACT_RANGE: f32 = 24.;
+
+pub struct InteractablePlugin;
+
+impl Plugin for InteractablePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((PropsPlugin, SignalPlugin))
+            .add_event::<InteractAction>()
+            .add_event::<InteractEvent>()
+            .add_systems(
+                Update,
+                (
+                    keyboard_interact_input
+                        .run_if(in_state(RunState::Playing).and_then(not(inventory_open))),
+                    interact,
+                    (open_chests, pull_levers),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Entity the player uses with the interact action when within [`INTERACT_RANGE`]
+#[derive(Component, Default)]
+pub struct Interactable;
+
+/// An event sent for an interact input
+#[derive(Event)]
+pub struct InteractAction;
+
+/// An event sent when the player uses `entity`
+#[derive(Event)]
+pub struct InteractEvent {
+    pub entity: Entity,
+}
+
+/// Sends [`InteractAction`] events based on keyboard input.
+fn keyboard_interact_input(
+    mut interact_event_writer: EventWriter<InteractAction>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::E) {
+        interact_event_writer.send(InteractAction);
+    }
+}
+
+/// Uses the closest [`Interactable`] within range of the player
+fn interact(
+    mut interact_action_reader: EventReader<InteractAction>,
+    mut interact_event_writer: EventWriter<InteractEvent>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    interactable_query: Query<(Entity, &GlobalTransform), With<Interactable>>,
+) {
+    if interact_action_reader.read().count() == 0 {
+        return;
+    }
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    let position = player_transform.translation().truncate();
+    let closest = interactable_query
+        .iter()
+        .map(|(entity, transform)| {
+            (
+                entity,
+                transform.translation().truncate().distance(position),
+            )
+        })
+        .filter(|(_, distance)| *distance <= INTERACT_RANGE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    if let Some((entity, _)) = closest {
+        interact_event_writer.send(InteractEvent { entity });
+    }
+}
@@ -0,0 +1,213 @@
+//! Chests, levers, pressure plates and breakable pots placed in LDtk
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::{
+    combat::{hitbox::*, Health},
+    layers::Layer,
+    ldtk_fields::{insert_from_fields, EntityFields, FieldError, FromFields},
+    loot::{LootDrop, LootEvent},
+};
+
+use super::{signal::*, InteractEvent, Interactable};
+
+/// Pots break on the first hit
+pub const POT_HEALTH: i32 = 1;
+/// Indices of the sprites of the props in the tileset
+const CHEST_OPENED_SPRITE: usize = 91;
+const LEVER_OFF_SPRITE: usize = 7;
+const LEVER_ON_SPRITE: usize = 8;
+
+pub(super) struct PropsPlugin;
+
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ChestBundle>("Chest")
+            .register_ldtk_entity::<LeverBundle>("Lever")
+            .register_ldtk_entity::<PressurePlateBundle>("PressurePlate")
+            .register_ldtk_entity::<PotBundle>("Pot")
+            .add_systems(
+                Update,
+                (
+                    insert_from_fields::<PropFields, Chest>,
+                    insert_from_fields::<PropFields, Pot>,
+                    load_prop_loot,
+                    press_plates,
+                ),
+            );
+    }
+}
+
+/// Custom fields of the `Chest` and `Pot` LDtk entities
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct PropFields {
+    /// Rolled when the chest opens or the pot breaks
+    pub loot_table: Option<String>,
+}
+
+impl FromFields for PropFields {
+    fn from_fields(fields: &EntityFields) -> Result<Self, FieldError> {
+        Ok(PropFields {
+            loot_table: fields.get_optional("LootTable")?,
+        })
+    }
+}
+
+/// Drops its loot and stays open once the player interacts with it
+#[derive(Component, Default)]
+pub struct Chest;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct ChestBundle {
+    pub chest: Chest,
+    pub interactable: Interactable,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    #[with(prop_collision_layers)]
+    pub collision_layers: CollisionLayers,
+}
+
+/// Toggles its [`Signal`] when the player interacts with it
+#[derive(Component, Default)]
+pub struct Lever;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct LeverBundle {
+    pub lever: Lever,
+    pub interactable: Interactable,
+    pub signal: Signal,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    #[with(prop_collision_layers)]
+    pub collision_layers: CollisionLayers,
+}
+
+/// Its [`Signal`] is on while the player or a mob stands on it
+#[derive(Component, Default)]
+pub struct PressurePlate;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct PressurePlateBundle {
+    pub pressure_plate: PressurePlate,
+    pub signal: Signal,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    pub sensor: Sensor,
+    #[with(trigger_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    pub colliding_entities: CollidingEntities,
+}
+
+/// Breaks when an [`Attack`](crate::combat::Attack) of the player hits it
+#[derive(Component, Default)]
+pub struct Pot;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct PotBundle {
+    pub pot: Pot,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    #[with(prop_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    #[with(pot_health)]
+    pub health: Health,
+    #[with(pot_hurtbox)]
+    pub hurtbox: Hurtbox,
+}
+
+fn load_prop_loot(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    prop_query: Query<(Entity, &PropFields), Added<PropFields>>,
+) {
+    for (entity, fields) in prop_query.iter() {
+        if let Some(loot_table) = &fields.loot_table {
+            commands
+                .entity(entity)
+                .insert(LootDrop(asset_server.load(loot_table)));
+        }
+    }
+}
+
+pub(super) fn open_chests(
+    mut commands: Commands,
+    mut interact_event_reader: EventReader<InteractEvent>,
+    mut loot_event_writer: EventWriter<LootEvent>,
+    mut chest_query: Query<
+        (&mut TextureAtlasSprite, &GlobalTransform, Option<&LootDrop>),
+        (With<Chest>, With<Interactable>),
+    >,
+) {
+    for event in interact_event_reader.read() {
+        let Ok((mut sprite, transform, loot_drop)) = chest_query.get_mut(event.entity) else {
+            continue;
+        };
+
+        sprite.index = CHEST_OPENED_SPRITE;
+        commands.entity(event.entity).remove::<Interactable>();
+        if let Some(loot_drop) = loot_drop {
+            loot_event_writer.send(LootEvent {
+                table: loot_drop.0.clone(),
+                position: transform.translation().truncate(),
+            });
+        }
+    }
+}
+
+pub(super) fn pull_levers(
+    mut interact_event_reader: EventReader<InteractEvent>,
+    mut lever_query: Query<(&mut Signal, &mut TextureAtlasSprite), With<Lever>>,
+) {
+    for event in interact_event_reader.read() {
+        let Ok((mut signal, mut sprite)) = lever_query.get_mut(event.entity) else {
+            continue;
+        };
+
+        signal.0 = !signal.0;
+        sprite.index = if signal.0 {
+            LEVER_ON_SPRITE
+        } else {
+            LEVER_OFF_SPRITE
+        };
+    }
+}
+
+fn press_plates(mut plate_query: Query<(&mut Signal, &CollidingEntities), With<PressurePlate>>) {
+    for (mut signal, colliding_entities) in plate_query.iter_mut() {
+        let pressed = !colliding_entities.is_empty();
+        if signal.0 != pressed {
+            signal.0 = pressed;
+        }
+    }
+}
+
+/// Props block characters and projectiles like walls
+fn prop_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Layer::terrain()
+}
+
+fn pot_health(_: &EntityInstance) -> Health {
+    Health::new(POT_HEALTH)
+}
+
+fn pot_hurtbox(_: &EntityInstance) -> Hurtbox {
+    Hurtbox::new(Team::Enemy)
+}
@@ -0,0 +1,261 @@
+//! Sources such as levers and pressure plates power the entities their `Targets` field
+//! references, receivers such as gates and spike traps react to being powered.
+//!
+//! A source can target any number of receivers, and a receiver is powered
+//! while at least one of the sources targeting it is on.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::{ldtk::EntityRef, prelude::*};
+use bevy_xpbd_2d::prelude::*;
+
+use crate::{
+    combat::{hitbox::*, Attack},
+    dungeon::collisions::Door,
+    layers::Layer,
+    ldtk_fields::{insert_from_fields, EntityFields, FieldError, FromFields},
+};
+
+pub const SPIKE_TRAP_DAMAGE: i32 = 10;
+/// Delay before a spike trap hurts the same target again
+pub const SPIKE_TRAP_REHIT_DELAY: Duration = Duration::from_millis(750);
+/// Index of the sprite of the spikes in the tileset
+const SPIKE_TRAP_ARMED_SPRITE: usize = 41;
+const SPIKE_TRAP_RETRACTED_SPRITE: usize = 48;
+
+pub(super) struct SignalPlugin;
+
+impl Plugin for SignalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<GateBundle>("Gate")
+            .register_ldtk_entity::<SpikeTrapBundle>("SpikeTrap")
+            .add_systems(
+                Update,
+                (
+                    insert_from_fields::<Targets, Signal>,
+                    (send_signals, (open_gates, retract_spike_traps)).chain(),
+                ),
+            );
+    }
+}
+
+/// State of a signal source, its [`Targets`] are powered while it is on
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal(pub bool);
+
+/// Receivers powered by a [`Signal`], read from the `Targets` field of the LDtk entity
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct Targets(pub Vec<EntityIid>);
+
+impl FromFields for Targets {
+    fn from_fields(fields: &EntityFields) -> Result<Self, FieldError> {
+        let targets: Vec<EntityRef> = fields.get("Targets")?;
+
+        Ok(Targets(
+            targets
+                .into_iter()
+                .map(|target| EntityIid::new(target.entity_iid))
+                .collect(),
+        ))
+    }
+}
+
+/// Receiver of signals, on while any of the sources targeting it is
+#[derive(Component, Default, Debug)]
+pub struct Powered {
+    sources: HashSet<Entity>,
+}
+
+impl Powered {
+    pub fn is_on(&self) -> bool {
+        !self.sources.is_empty()
+    }
+}
+
+/// Updates the receivers targeted by the sources that changed or despawned
+#[allow(clippy::type_complexity)]
+fn send_signals(
+    source_query: Query<(Entity, &Signal, &Targets), Or<(Changed<Signal>, Changed<Targets>)>>,
+    mut removed_sources: RemovedComponents<Signal>,
+    mut receiver_query: Query<(&EntityIid, &mut Powered)>,
+) {
+    for source in removed_sources.read() {
+        for (_, mut powered) in receiver_query.iter_mut() {
+            if powered.sources.contains(&source) {
+                powered.sources.remove(&source);
+            }
+        }
+    }
+
+    for (source, signal, targets) in source_query.iter() {
+        for (iid, mut powered) in receiver_query.iter_mut() {
+            if !targets.0.contains(iid) || powered.sources.contains(&source) == signal.0 {
+                continue;
+            }
+
+            if signal.0 {
+                powered.sources.insert(source);
+            } else {
+                powered.sources.remove(&source);
+            }
+        }
+    }
+}
+
+/// Collider blocking the way until it is [`Powered`]
+#[derive(Component, Default)]
+pub struct Gate;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct GateBundle {
+    pub gate: Gate,
+    pub powered: Powered,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    #[with(gate_collision_layers)]
+    pub collision_layers: CollisionLayers,
+}
+
+/// Hurts the player standing on it unless it is [`Powered`]
+#[derive(Component, Default)]
+pub struct SpikeTrap;
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct SpikeTrapBundle {
+    pub spike_trap: SpikeTrap,
+    pub powered: Powered,
+    #[sprite_sheet_bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[with(entity_collider)]
+    pub collider: Collider,
+    #[with(static_body)]
+    pub rigid_body: RigidBody,
+    pub sensor: Sensor,
+    #[with(trigger_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    #[with(spike_trap_hitbox)]
+    pub hitbox: Hitbox,
+    #[with(spike_trap_attack)]
+    pub attack: Attack,
+}
+
+fn open_gates(
+    mut gate_query: Query<
+        (&Powered, &mut CollisionLayers, &mut Visibility),
+        (With<Gate>, Changed<Powered>),
+    >,
+) {
+    for (powered, mut layers, mut visibility) in gate_query.iter_mut() {
+        if powered.is_on() {
+            *layers = Door::open();
+            *visibility = Visibility::Hidden;
+        } else {
+            *layers = Door::closed();
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn retract_spike_traps(
+    mut trap_query: Query<
+        (&Powered, &mut CollisionLayers, &mut TextureAtlasSprite),
+        (With<SpikeTrap>, Changed<Powered>),
+    >,
+) {
+    for (powered, mut layers, mut sprite) in trap_query.iter_mut() {
+        if powered.is_on() {
+            *layers = CollisionLayers::none();
+            sprite.index = SPIKE_TRAP_RETRACTED_SPRITE;
+        } else {
+            *layers = Layer::trigger();
+            sprite.index = SPIKE_TRAP_ARMED_SPRITE;
+        }
+    }
+}
+
+/// Collider covering the whole LDtk entity
+pub(super) fn entity_collider(entity_instance: &EntityInstance) -> Collider {
+    Collider::cuboid(entity_instance.width as f32, entity_instance.height as f32)
+}
+
+pub(super) fn static_body(_: &EntityInstance) -> RigidBody {
+    RigidBody::Static
+}
+
+pub(super) fn trigger_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Layer::trigger()
+}
+
+fn gate_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Door::closed()
+}
+
+fn spike_trap_hitbox(_: &EntityInstance) -> Hitbox {
+    Hitbox::new(Team::Enemy).with_rehit_delay(SPIKE_TRAP_REHIT_DELAY)
+}
+
+fn spike_trap_attack(_: &EntityInstance) -> Attack {
+    Attack::new(SPIKE_TRAP_DAMAGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::FieldValue;
+
+    use super::*;
+
+    #[test]
+    fn targets_are_read_from_entity_references() {
+        let reference = |iid: &str| EntityRef {
+            entity_iid: iid.into(),
+            layer_iid: "layer".into(),
+            level_iid: "level".into(),
+            world_iid: "world".into(),
+        };
+        let value = FieldValue::EntityRefs(vec![Some(reference("gate")), Some(reference("trap"))]);
+
+        let targets = Targets::from_fields(&EntityFields::new(
+            "Level_0",
+            "Lever",
+            "iid",
+            vec![("Targets", "Array<EntityRef>", &value)],
+        ));
+
+        assert_eq!(
+            targets,
+            Ok(Targets(vec![
+                EntityIid::new("gate"),
+                EntityIid::new("trap")
+            ]))
+        );
+    }
+
+    #[test]
+    fn receivers_stay_powered_while_any_source_is_on() {
+        let mut app = App::new();
+        app.add_systems(Update, send_signals);
+        let gate = app
+            .world
+            .spawn((EntityIid::new("gate"), Powered::default()))
+            .id();
+        let targets = Targets(vec![EntityIid::new("gate")]);
+        let lever = app.world.spawn((Signal(true), targets.clone())).id();
+        let plate = app.world.spawn((Signal(true), targets)).id();
+
+        app.update();
+        assert!(app.world.get::<Powered>(gate).unwrap().is_on());
+
+        app.world.get_mut::<Signal>(lever).unwrap().0 = false;
+        app.update();
+        assert!(app.world.get::<Powered>(gate).unwrap().is_on());
+
+        app.world.despawn(plate);
+        app.update();
+        assert!(!app.world.get::<Powered>(gate).unwrap().is_on());
+    }
+}
@@ -0,0 +1,493 @@
+//! Items carried and equipped by the player
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::{
+    combat::bullet_pattern::ProjectileSpec,
+    player::Player,
+    ron_asset::RonAssetPlugin,
+    stats::{Modifier, ModifierSource, Stats},
+};
+
+use self::ui::*;
+
+pub mod ui;
+
+/// File listing every item of the game, relative to the assets folder
+pub const ITEM_CATALOG_PATH: &str = "items/catalog.items.ron";
+pub const INVENTORY_SIZE: usize = 20;
+
+const ITEM_TILESET: &str = "kenney-tiny-dungeon/tilemap_packed.png";
+const ITEM_TILE_SIZE: f32 = 16.;
+const ITEM_TILESET_COLUMNS: usize = 12;
+const ITEM_TILESET_ROWS: usize = 11;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RonAssetPlugin::<ItemCatalog>::new(&["items.ron"]),
+            InventoryUiPlugin,
+        ))
+        .init_resource::<Items>()
+        .init_resource::<ItemAtlas>()
+        .add_systems(Update, apply_equipment_modifiers);
+    }
+}
+
+/// Definitions of the items by identifier, loaded from [`ITEM_CATALOG_PATH`]
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct ItemCatalog(pub HashMap<String, ItemDefinition>);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ItemDefinition {
+    pub name: String,
+    /// Index of the item's sprite in the tileset
+    pub sprite: usize,
+    /// Number of items a single inventory slot can hold
+    #[serde(default = "one")]
+    pub max_stack: u32,
+    /// Equipment slot the item can be equipped in, if any
+    #[serde(default)]
+    pub slot: Option<EquipmentSlot>,
+    /// Applied to the player's [`Stats`] while the item is equipped
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    /// Attack performed on [`AttackAction::Weapon`](crate::player::attack::AttackAction)
+    /// when the item is the equipped weapon
+    #[serde(default)]
+    pub weapon: Option<WeaponAttack>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum WeaponAttack {
+    /// Fires a projectile toward the target
+    Projectile(ProjectileSpec),
+    /// Swings in front of the player
+    Melee(MeleeSpec),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MeleeSpec {
+    pub damage: i32,
+    /// Speed at which targets are pushed away
+    #[serde(default)]
+    pub knockback: f32,
+    /// Distance from the player to the center of the swing
+    pub reach: f32,
+    /// Size of the swing along and across the aim direction
+    pub size: (f32, f32),
+    /// Seconds during which the swing hits
+    pub duration: f32,
+    /// Seconds during which targets hit are stunned
+    #[serde(default)]
+    pub stun: f32,
+}
+
+impl ItemCatalog {
+    pub fn max_stack(&self, item: &str) -> u32 {
+        self.0
+            .get(item)
+            .map_or(1, |definition| definition.max_stack)
+    }
+}
+
+/// Handle keeping the [`ItemCatalog`] loaded
+#[derive(Resource)]
+pub struct Items(pub Handle<ItemCatalog>);
+
+impl FromWorld for Items {
+    fn from_world(world: &mut World) -> Self {
+        Items(world.resource::<AssetServer>().load(ITEM_CATALOG_PATH))
+    }
+}
+
+/// Tileset the item sprites are taken from
+#[derive(Resource)]
+pub struct ItemAtlas(pub Handle<TextureAtlas>);
+
+impl FromWorld for ItemAtlas {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world.resource::<AssetServer>().load(ITEM_TILESET);
+        let atlas = TextureAtlas::from_grid(
+            texture,
+            Vec2::splat(ITEM_TILE_SIZE),
+            ITEM_TILESET_COLUMNS,
+            ITEM_TILESET_ROWS,
+            None,
+            None,
+        );
+
+        ItemAtlas(world.resource_mut::<Assets<TextureAtlas>>().add(atlas))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+/// Fixed grid of slots, each holding a stack of a single item
+#[derive(Component, Debug)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new(INVENTORY_SIZE)
+    }
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Inventory {
+            slots: vec![None; size],
+        }
+    }
+
+    /// Adds `count` items, topping up the stacks of the same item before using empty slots.
+    /// Returns the number of items that did not fit.
+    pub fn add(&mut self, item: &str, mut count: u32, max_stack: u32) -> u32 {
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.item == item && stack.count < max_stack {
+                let added = count.min(max_stack - stack.count);
+                stack.count += added;
+                count -= added;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let added = count.min(max_stack);
+            *slot = Some(ItemStack {
+                item: item.into(),
+                count: added,
+            });
+            count -= added;
+        }
+
+        count
+    }
+
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 3] = [
+        EquipmentSlot::Weapon,
+        EquipmentSlot::Armor,
+        EquipmentSlot::Trinket,
+    ];
+}
+
+/// Items equipped by the player, by slot
+#[derive(Component, Default, Debug)]
+pub struct Equipment {
+    weapon: Option<String>,
+    armor: Option<String>,
+    trinket: Option<String>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&str> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon.as_deref(),
+            EquipmentSlot::Armor => self.armor.as_deref(),
+            EquipmentSlot::Trinket => self.trinket.as_deref(),
+        }
+    }
+
+    /// Equips `item` in `slot` and returns the item it replaces
+    pub fn set(&mut self, slot: EquipmentSlot, item: Option<String>) -> Option<String> {
+        let equipped = match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Trinket => &mut self.trinket,
+        };
+        std::mem::replace(equipped, item)
+    }
+
+    /// Modifiers of all the equipped items
+    pub fn modifiers(&self, catalog: &ItemCatalog) -> Vec<Modifier> {
+        EquipmentSlot::ALL
+            .into_iter()
+            .filter_map(|slot| catalog.0.get(self.get(slot)?))
+            .flat_map(|definition| definition.modifiers.iter().copied())
+            .collect()
+    }
+
+    pub fn weapon<'a>(&self, catalog: &'a ItemCatalog) -> Option<&'a WeaponAttack> {
+        catalog
+            .0
+            .get(self.get(EquipmentSlot::Weapon)?)?
+            .weapon
+            .as_ref()
+    }
+}
+
+/// A slot of the inventory or of the equipment
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotRef {
+    Inventory(usize),
+    Equipment(EquipmentSlot),
+}
+
+/// Moves what is in slot `from` to slot `to`, merging stacks of the same item and swapping
+/// otherwise. Returns whether anything moved, equipment slots only accept matching items.
+pub fn move_item(
+    inventory: &mut Inventory,
+    equipment: &mut Equipment,
+    catalog: &ItemCatalog,
+    from: SlotRef,
+    to: SlotRef,
+) -> bool {
+    let fits = |item: &str, slot: EquipmentSlot| {
+        catalog
+            .0
+            .get(item)
+            .is_some_and(|definition| definition.slot == Some(slot))
+    };
+
+    match (from, to) {
+        (SlotRef::Inventory(from), SlotRef::Inventory(to)) => {
+            if from == to || from >= inventory.slots.len() || to >= inventory.slots.len() {
+                return false;
+            }
+            match (&inventory.slots[from], &inventory.slots[to]) {
+                (None, _) => false,
+                (Some(source), Some(target)) if source.item == target.item => {
+                    let max_stack = catalog.max_stack(&source.item);
+                    let moved = source.count.min(max_stack.saturating_sub(target.count));
+                    if moved == 0 {
+                        return false;
+                    }
+                    if let Some(target) = &mut inventory.slots[to] {
+                        target.count += moved;
+                    }
+                    if let Some(source) = &mut inventory.slots[from] {
+                        source.count -= moved;
+                        if source.count == 0 {
+                            inventory.slots[from] = None;
+                        }
+                    }
+                    true
+                }
+                _ => {
+                    inventory.slots.swap(from, to);
+                    true
+                }
+            }
+        }
+        (SlotRef::Inventory(from), SlotRef::Equipment(slot)) => {
+            let Some(Some(source)) = inventory.slots.get(from) else {
+                return false;
+            };
+            if !fits(&source.item, slot) {
+                return false;
+            }
+            let item = source.item.clone();
+
+            if source.count == 1 {
+                let previous = equipment.set(slot, Some(item));
+                inventory.slots[from] = previous.map(|item| ItemStack { item, count: 1 });
+            } else {
+                if let Some(previous) = equipment.get(slot) {
+                    let max_stack = catalog.max_stack(previous);
+                    if inventory.add(previous, 1, max_stack) > 0 {
+                        return false;
+                    }
+                }
+                if let Some(source) = &mut inventory.slots[from] {
+                    source.count -= 1;
+                }
+                equipment.set(slot, Some(item));
+            }
+            true
+        }
+        (SlotRef::Equipment(slot), SlotRef::Inventory(to)) => {
+            let (Some(item), Some(target)) = (equipment.get(slot), inventory.slots.get(to)) else {
+                return false;
+            };
+            match target {
+                None => {}
+                Some(target) if target.count == 1 && fits(&target.item, slot) => {}
+                Some(_) => return false,
+            }
+
+            let item = item.to_string();
+            let replacement = inventory.slots[to].take().map(|stack| stack.item);
+            equipment.set(slot, replacement);
+            inventory.slots[to] = Some(ItemStack { item, count: 1 });
+            true
+        }
+        (SlotRef::Equipment(_), SlotRef::Equipment(_)) => false,
+    }
+}
+
+/// Replaces the equipment modifiers of the player's [`Stats`] when the equipment changes
+fn apply_equipment_modifiers(
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut catalog_events: EventReader<AssetEvent<ItemCatalog>>,
+    mut player_query: Query<(Ref<Equipment>, &mut Stats), With<Player>>,
+) {
+    let catalog_changed = catalog_events.read().count() > 0;
+    let Some(catalog) = catalogs.get(&items.0) else {
+        return;
+    };
+
+    for (equipment, mut stats) in player_query.iter_mut() {
+        if !equipment.is_changed() && !catalog_changed {
+            continue;
+        }
+
+        stats.set_modifiers(ModifierSource::Equipment, equipment.modifiers(catalog));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> ItemCatalog {
+        ron::from_str(&std::fs::read_to_string(format!("assets/{ITEM_CATALOG_PATH}")).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn bundled_catalog_is_valid() {
+        for (item, definition) in catalog().0 {
+            assert!(definition.max_stack > 0, "{item} should stack");
+            if definition.weapon.is_some() {
+                assert_eq!(
+                    definition.slot,
+                    Some(EquipmentSlot::Weapon),
+                    "{item} should be equipped as a weapon"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn adding_tops_up_stacks_before_using_empty_slots() {
+        let mut inventory = Inventory::new(3);
+        assert_eq!(inventory.add("gem", 7, 5), 0);
+        assert_eq!(inventory.add("gem", 2, 5), 0);
+
+        assert_eq!(inventory.slots[0].as_ref().unwrap().count, 5);
+        assert_eq!(inventory.slots[1].as_ref().unwrap().count, 4);
+        assert_eq!(inventory.slots[2], None);
+    }
+
+    #[test]
+    fn adding_to_a_full_inventory_returns_the_rest() {
+        let mut inventory = Inventory::new(2);
+
+        assert_eq!(inventory.add("gem", 12, 5), 2);
+        assert_eq!(inventory.count("gem"), 10);
+    }
+
+    #[test]
+    fn moving_onto_the_same_item_merges_stacks() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(3);
+        let mut equipment = Equipment::default();
+        inventory.slots[0] = Some(ItemStack {
+            item: "gem".into(),
+            count: 3,
+        });
+        inventory.slots[2] = Some(ItemStack {
+            item: "gem".into(),
+            count: 2,
+        });
+
+        assert!(move_item(
+            &mut inventory,
+            &mut equipment,
+            &catalog,
+            SlotRef::Inventory(0),
+            SlotRef::Inventory(2)
+        ));
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.count("gem"), 5);
+    }
+
+    #[test]
+    fn equipping_swaps_with_the_equipped_item() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(3);
+        let mut equipment = Equipment::default();
+        equipment.set(EquipmentSlot::Weapon, Some("dagger".into()));
+        inventory.add("fire_staff", 1, 1);
+
+        assert!(move_item(
+            &mut inventory,
+            &mut equipment,
+            &catalog,
+            SlotRef::Inventory(0),
+            SlotRef::Equipment(EquipmentSlot::Weapon)
+        ));
+        assert_eq!(equipment.get(EquipmentSlot::Weapon), Some("fire_staff"));
+        assert_eq!(inventory.count("dagger"), 1);
+    }
+
+    #[test]
+    fn equipment_slots_only_accept_matching_items() {
+        let catalog = catalog();
+        let mut inventory = Inventory::new(3);
+        let mut equipment = Equipment::default();
+        inventory.add("dagger", 1, 1);
+
+        assert!(!move_item(
+            &mut inventory,
+            &mut equipment,
+            &catalog,
+            SlotRef::Inventory(0),
+            SlotRef::Equipment(EquipmentSlot::Armor)
+        ));
+        assert_eq!(equipment.get(EquipmentSlot::Armor), None);
+        assert_eq!(inventory.count("dagger"), 1);
+    }
+
+    #[test]
+    fn equipped_modifiers_add_up() {
+        let catalog = catalog();
+        let mut equipment = Equipment::default();
+        equipment.set(EquipmentSlot::Armor, Some("round_shield".into()));
+        equipment.set(EquipmentSlot::Trinket, Some("fury_tonic".into()));
+
+        let modifiers = equipment.modifiers(&catalog);
+        assert_eq!(
+            modifiers,
+            [
+                catalog.0["round_shield"].modifiers.clone(),
+                catalog.0["fury_tonic"].modifiers.clone()
+            ]
+            .concat()
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::*;
+
+const INVENTORY_COLUMNS: usize = 5;
+const SLOT_SIZE: f32 = 56.;
+const SLOT_GAP: f32 = 6.;
+const INVENTORY_FONT_SIZE: f32 = 28.;
+const COUNT_FONT_SIZE: f32 = 16.;
+const SLOT_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_SLOT_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+pub(super) struct InventoryUiPlugin;
+
+impl Plugin for InventoryUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DraggedSlot>()
+            .add_systems(Startup, spawn_inventory_screen)
+            .add_systems(
+                Update,
+                (
+                    toggle_inventory_screen,
+                    slot_hover_color,
+                    (start_drag, drop_dragged_item, move_drag_icon)
+                        .chain()
+                        .run_if(inventory_open),
+                    update_slot_contents,
+                ),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct InventoryScreen;
+
+/// Whether the [`InventoryScreen`] is shown, mouse attacks are ignored while it is
+pub fn inventory_open(screen_query: Query<&Visibility, With<InventoryScreen>>) -> bool {
+    screen_query
+        .iter()
+        .any(|visibility| *visibility == Visibility::Visible)
+}
+
+/// Sprite of the item held in a slot
+#[derive(Component)]
+struct SlotIcon(SlotRef);
+
+/// Size of the stack held in a slot
+#[derive(Component)]
+struct SlotCount(SlotRef);
+
+/// Follows the cursor while an item is dragged
+#[derive(Component)]
+struct DragIcon;
+
+/// Slot of the item being dragged with the mouse
+#[derive(Resource, Default)]
+struct DraggedSlot(Option<SlotRef>);
+
+/// Identifier and stack size of what is in `slot`
+fn slot_content<'a>(
+    inventory: &'a Inventory,
+    equipment: &'a Equipment,
+    slot: SlotRef,
+) -> Option<(&'a str, u32)> {
+    match slot {
+        SlotRef::Inventory(index) => inventory
+            .slots
+            .get(index)?
+            .as_ref()
+            .map(|stack| (stack.item.as_str(), stack.count)),
+        SlotRef::Equipment(slot) => equipment.get(slot).map(|item| (item, 1)),
+    }
+}
+
+fn spawn_inventory_screen(mut commands: Commands, item_atlas: Res<ItemAtlas>) {
+    let text_style = TextStyle {
+        font_size: INVENTORY_FONT_SIZE,
+        color: Color::WHITE,
+        ..default()
+    };
+    let grid_width = INVENTORY_COLUMNS as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+
+    commands
+        .spawn((
+            InventoryScreen,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section("Equipment", text_style.clone()));
+            screen
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(SLOT_GAP),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for slot in EquipmentSlot::ALL {
+                        spawn_slot(row, &item_atlas, SlotRef::Equipment(slot));
+                    }
+                });
+
+            screen.spawn(TextBundle::from_section("Inventory", text_style.clone()));
+            screen
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(grid_width),
+                        flex_wrap: FlexWrap::Wrap,
+                        row_gap: Val::Px(SLOT_GAP),
+                        column_gap: Val::Px(SLOT_GAP),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for index in 0..INVENTORY_SIZE {
+                        spawn_slot(grid, &item_atlas, SlotRef::Inventory(index));
+                    }
+                });
+
+            screen.spawn((
+                DragIcon,
+                AtlasImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(SLOT_SIZE),
+                        height: Val::Px(SLOT_SIZE),
+                        ..default()
+                    },
+                    texture_atlas: item_atlas.0.clone(),
+                    visibility: Visibility::Hidden,
+                    z_index: ZIndex::Global(6),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_slot(parent: &mut ChildBuilder, item_atlas: &ItemAtlas, slot: SlotRef) {
+    parent
+        .spawn((
+            slot,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(SLOT_SIZE),
+                    height: Val::Px(SLOT_SIZE),
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                background_color: SLOT_COLOR.into(),
+                ..default()
+            },
+        ))
+        .with_children(|button| {
+            button.spawn((
+                SlotIcon(slot),
+                AtlasImageBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    texture_atlas: item_atlas.0.clone(),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ));
+            button.spawn((
+                SlotCount(slot),
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: COUNT_FONT_SIZE,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(4.),
+                    bottom: Val::Px(2.),
+                    ..default()
+                }),
+            ));
+        });
+}
+
+fn toggle_inventory_screen(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut screen_query: Query<&mut Visibility, With<InventoryScreen>>,
+    mut dragged_slot: ResMut<DraggedSlot>,
+) {
+    if keyboard_input.just_pressed(KeyCode::I) {
+        for mut visibility in screen_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+        dragged_slot.0 = None;
+    }
+}
+
+fn slot_hover_color(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<SlotRef>),
+    >,
+) {
+    for (interaction, mut background_color) in interaction_query.iter_mut() {
+        *background_color = match interaction {
+            Interaction::None => SLOT_COLOR.into(),
+            _ => HOVERED_SLOT_COLOR.into(),
+        };
+    }
+}
+
+fn start_drag(
+    interaction_query: Query<(&Interaction, &SlotRef), Changed<Interaction>>,
+    player_query: Query<(&Inventory, &Equipment), With<Player>>,
+    mut dragged_slot: ResMut<DraggedSlot>,
+) {
+    let Ok((inventory, equipment)) = player_query.get_single() else {
+        return;
+    };
+
+    for (interaction, slot) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed
+            && slot_content(inventory, equipment, *slot).is_some()
+        {
+            dragged_slot.0 = Some(*slot);
+        }
+    }
+}
+
+/// Moves the dragged item to the slot under the cursor when the mouse button is released
+fn drop_dragged_item(
+    mouse_button_input: Res<Input<MouseButton>>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    slot_query: Query<(&SlotRef, &Node, &GlobalTransform)>,
+    mut player_query: Query<(&mut Inventory, &mut Equipment), With<Player>>,
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut dragged_slot: ResMut<DraggedSlot>,
+) {
+    if !mouse_button_input.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(from) = dragged_slot.0.take() else {
+        return;
+    };
+    let (Ok(window), Ok((mut inventory, mut equipment)), Some(catalog)) = (
+        primary_window.get_single(),
+        player_query.get_single_mut(),
+        catalogs.get(&items.0),
+    ) else {
+        return;
+    };
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+
+    if let Some((to, _, _)) = slot_query
+        .iter()
+        .find(|(_, node, transform)| node.logical_rect(transform).contains(cursor_position))
+    {
+        move_item(&mut inventory, &mut equipment, catalog, from, *to);
+    }
+}
+
+fn move_drag_icon(
+    dragged_slot: Res<DraggedSlot>,
+    primary_window: Query<&Window, With<PrimaryWindow>>,
+    player_query: Query<(&Inventory, &Equipment), With<Player>>,
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut icon_query: Query<(&mut Style, &mut UiTextureAtlasImage, &mut Visibility), With<DragIcon>>,
+) {
+    let Ok((mut style, mut atlas_image, mut visibility)) = icon_query.get_single_mut() else {
+        return;
+    };
+
+    let dragged = dragged_slot.0.and_then(|slot| {
+        let (inventory, equipment) = player_query.get_single().ok()?;
+        let (item, _) = slot_content(inventory, equipment, slot)?;
+        let cursor_position = primary_window.get_single().ok()?.cursor_position()?;
+        Some((catalogs.get(&items.0)?.0.get(item)?.sprite, cursor_position))
+    });
+
+    match dragged {
+        Some((sprite, cursor_position)) => {
+            atlas_image.index = sprite;
+            style.left = Val::Px(cursor_position.x - SLOT_SIZE / 2.);
+            style.top = Val::Px(cursor_position.y - SLOT_SIZE / 2.);
+            *visibility = Visibility::Visible;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+fn update_slot_contents(
+    player_query: Query<(Ref<Inventory>, Ref<Equipment>), With<Player>>,
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut catalog_events: EventReader<AssetEvent<ItemCatalog>>,
+    mut icon_query: Query<(&SlotIcon, &mut UiTextureAtlasImage, &mut Visibility)>,
+    mut count_query: Query<(&SlotCount, &mut Text)>,
+) {
+    let catalog_changed = catalog_events.read().count() > 0;
+    let (Ok((inventory, equipment)), Some(catalog)) =
+        (player_query.get_single(), catalogs.get(&items.0))
+    else {
+        return;
+    };
+    if !inventory.is_changed() && !equipment.is_changed() && !catalog_changed {
+        return;
+    }
+
+    for (SlotIcon(slot), mut atlas_image, mut visibility) in icon_query.iter_mut() {
+        match slot_content(&inventory, &equipment, *slot).and_then(|(item, _)| catalog.0.get(item))
+        {
+            Some(definition) => {
+                atlas_image.index = definition.sprite;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+
+    for (SlotCount(slot), mut text) in count_query.iter_mut() {
+        text.sections[0].value = match slot_content(&inventory, &equipment, *slot) {
+            Some((_, count)) if count > 1 => count.to_string(),
+            _ => String::new(),
+        };
+    }
+}
@@ -0,0 +1,113 @@
+use bevy_xpbd_2d::prelude::*;
+
+/// Physics layers of the game, physics skips pairs of colliders whose layers do not interact
+#[derive(Clone, Copy, Debug)]
+pub enum Layer {
+    Player,
+    Mob,
+    PlayerProjectile,
+    EnemyProjectile,
+    Terrain,
+    Door,
+    Pickup,
+    /// Sensors reacting to the characters standing on them, e.g. pressure plates
+    Trigger,
+}
+
+const LAYER_COUNT: u32 = 8;
+
+impl PhysicsLayer for Layer {
+    fn to_bits(&self) -> u32 {
+        1 << *self as u32
+    }
+
+    fn all_bits() -> u32 {
+        (1 << LAYER_COUNT) - 1
+    }
+}
+
+/// Characters mask the projectiles of both teams, only the layers of projectiles decide
+/// whether [`FriendlyFire`](crate::combat::FriendlyFire) lets them hit their own team
+impl Layer {
+    pub fn player() -> CollisionLayers {
+        CollisionLayers::new(
+            [Layer::Player],
+            [
+                Layer::Mob,
+                Layer::PlayerProjectile,
+                Layer::EnemyProjectile,
+                Layer::Terrain,
+                Layer::Door,
+                Layer::Pickup,
+                Layer::Trigger,
+            ],
+        )
+    }
+
+    pub fn mob() -> CollisionLayers {
+        CollisionLayers::new(
+            [Layer::Mob],
+            [
+                Layer::Player,
+                Layer::Mob,
+                Layer::PlayerProjectile,
+                Layer::EnemyProjectile,
+                Layer::Terrain,
+                Layer::Door,
+                Layer::Trigger,
+            ],
+        )
+    }
+
+    /// Projectiles fired by the player, they also hit the player when `friendly_fire` is set
+    pub fn player_projectile(friendly_fire: bool) -> CollisionLayers {
+        let layers = CollisionLayers::new(
+            [Layer::PlayerProjectile],
+            [Layer::Mob, Layer::EnemyProjectile, Layer::Terrain],
+        );
+
+        if friendly_fire {
+            layers.add_mask(Layer::Player)
+        } else {
+            layers
+        }
+    }
+
+    /// Projectiles fired by mobs, they also hit mobs when `friendly_fire` is set
+    pub fn enemy_projectile(friendly_fire: bool) -> CollisionLayers {
+        let layers = CollisionLayers::new(
+            [Layer::EnemyProjectile],
+            [Layer::Player, Layer::PlayerProjectile, Layer::Terrain],
+        );
+
+        if friendly_fire {
+            layers.add_mask(Layer::Mob)
+        } else {
+            layers
+        }
+    }
+
+    pub fn terrain() -> CollisionLayers {
+        CollisionLayers::new(
+            [Layer::Terrain],
+            [
+                Layer::Player,
+                Layer::Mob,
+                Layer::PlayerProjectile,
+                Layer::EnemyProjectile,
+            ],
+        )
+    }
+
+    pub fn door() -> CollisionLayers {
+        CollisionLayers::new([Layer::Door], [Layer::Player, Layer::Mob])
+    }
+
+    pub fn pickup() -> CollisionLayers {
+        CollisionLayers::new([Layer::Pickup], [Layer::Player])
+    }
+
+    pub fn trigger() -> CollisionLayers {
+        CollisionLayers::new([Layer::Trigger], [Layer::Player, Layer::Mob])
+    }
+}
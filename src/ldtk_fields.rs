@@ -0,0 +1,482 @@
+//! Typed reading of the custom fields of LDtk entities
+//!
+//! Components implementing [`FromFields`] are inserted by [`insert_from_fields`], which logs an
+//! error naming the level, the entity and the field when a field is missing or has another type.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{
+    ldtk::{EntityRef, FieldValue},
+    prelude::*,
+};
+use thiserror::Error;
+
+/// Name used for entities that are not in a level, such as worldly ones
+const UNKNOWN_LEVEL: &str = "<none>";
+
+#[derive(Error, Debug, PartialEq)]
+#[error("level {level}, entity {entity} {iid}: field `{field}` {kind}")]
+pub struct FieldError {
+    pub level: String,
+    /// Identifier of the entity definition, e.g. `Mob`
+    pub entity: String,
+    pub iid: String,
+    pub field: String,
+    pub kind: FieldErrorKind,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FieldErrorKind {
+    #[error("is missing")]
+    Missing,
+    #[error("has no value")]
+    Null,
+    #[error("should be {expected} but is {found}")]
+    WrongType {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("has the unknown value `{0}`")]
+    UnknownValue(String),
+}
+
+/// Outcome of reading a [`FieldValue`] as a [`FieldType`]
+#[derive(Debug, PartialEq)]
+pub enum FieldRead<T> {
+    Value(T),
+    /// The field is empty, or an element of the array is
+    Null,
+    WrongType,
+    /// The value has the right type but the game does not know it, e.g. a new enum value
+    UnknownValue(String),
+}
+
+impl<T> From<Option<T>> for FieldRead<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => FieldRead::Value(value),
+            None => FieldRead::Null,
+        }
+    }
+}
+
+/// Reads an LDtk enum value as the Rust enum returned by `parse`
+pub fn read_enum<T>(value: &FieldValue, parse: impl FnOnce(&str) -> Option<T>) -> FieldRead<T> {
+    match value {
+        FieldValue::Enum(Some(value)) => {
+            parse(value).map_or_else(|| FieldRead::UnknownValue(value.clone()), FieldRead::Value)
+        }
+        FieldValue::Enum(None) => FieldRead::Null,
+        _ => FieldRead::WrongType,
+    }
+}
+
+/// Whether a single value field is empty, arrays are never
+fn is_null(value: &FieldValue) -> bool {
+    matches!(
+        value,
+        FieldValue::Int(None)
+            | FieldValue::Float(None)
+            | FieldValue::String(None)
+            | FieldValue::FilePath(None)
+            | FieldValue::Enum(None)
+            | FieldValue::EntityRef(None)
+            | FieldValue::Point(None)
+    )
+}
+
+/// A type the value of an LDtk field can be read as
+///
+/// LDtk enums are read as [`String`]s, or as Rust enums implementing this trait.
+pub trait FieldType: Sized {
+    /// LDtk type of the field, shown in errors
+    const EXPECTED: &'static str;
+
+    fn read(value: &FieldValue) -> FieldRead<Self>;
+}
+
+impl FieldType for i32 {
+    const EXPECTED: &'static str = "Int";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Int(value) => (*value).into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for f32 {
+    const EXPECTED: &'static str = "Float";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Float(value) => (*value).into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for bool {
+    const EXPECTED: &'static str = "Bool";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Bool(value) => FieldRead::Value(*value),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+/// Strings, file paths and enum values
+impl FieldType for String {
+    const EXPECTED: &'static str = "String";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::String(value) | FieldValue::FilePath(value) | FieldValue::Enum(value) => {
+                value.clone().into()
+            }
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+/// Grid coordinates of a point
+impl FieldType for IVec2 {
+    const EXPECTED: &'static str = "Point";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Point(value) => (*value).into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for EntityRef {
+    const EXPECTED: &'static str = "EntityRef";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::EntityRef(value) => value.clone().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<i32> {
+    const EXPECTED: &'static str = "Array<Int>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Ints(values) => values.iter().copied().collect::<Option<_>>().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<f32> {
+    const EXPECTED: &'static str = "Array<Float>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Floats(values) => values.iter().copied().collect::<Option<_>>().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<bool> {
+    const EXPECTED: &'static str = "Array<Bool>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Bools(values) => FieldRead::Value(values.clone()),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<String> {
+    const EXPECTED: &'static str = "Array<String>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Strings(values)
+            | FieldValue::FilePaths(values)
+            | FieldValue::Enums(values) => values.iter().cloned().collect::<Option<_>>().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<IVec2> {
+    const EXPECTED: &'static str = "Array<Point>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::Points(values) => values.iter().copied().collect::<Option<_>>().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+impl FieldType for Vec<EntityRef> {
+    const EXPECTED: &'static str = "Array<EntityRef>";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        match value {
+            FieldValue::EntityRefs(values) => values.iter().cloned().collect::<Option<_>>().into(),
+            _ => FieldRead::WrongType,
+        }
+    }
+}
+
+/// Fields of an LDtk entity, read by identifier
+pub struct EntityFields<'a> {
+    level: String,
+    entity: &'a str,
+    iid: &'a str,
+    /// Identifier, LDtk type and value of each field
+    fields: Vec<(&'a str, &'a str, &'a FieldValue)>,
+}
+
+impl<'a> EntityFields<'a> {
+    pub fn new(
+        level: impl Into<String>,
+        entity: &'a str,
+        iid: &'a str,
+        fields: Vec<(&'a str, &'a str, &'a FieldValue)>,
+    ) -> Self {
+        EntityFields {
+            level: level.into(),
+            entity,
+            iid,
+            fields,
+        }
+    }
+
+    pub fn from_entity_instance(entity_instance: &'a EntityInstance, level: &str) -> Self {
+        EntityFields::new(
+            level,
+            &entity_instance.identifier,
+            &entity_instance.iid,
+            entity_instance
+                .field_instances
+                .iter()
+                .map(|field| {
+                    (
+                        field.identifier.as_str(),
+                        field.field_instance_type.as_str(),
+                        &field.value,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Value of a field that must be set
+    pub fn get<T: FieldType>(&self, field: &str) -> Result<T, FieldError> {
+        self.get_optional(field)?
+            .ok_or_else(|| self.error(field, FieldErrorKind::Null))
+    }
+
+    /// Value of a field that can be left empty, the field must still exist
+    pub fn get_optional<T: FieldType>(&self, field: &str) -> Result<Option<T>, FieldError> {
+        let Some((_, field_type, value)) = self
+            .fields
+            .iter()
+            .find(|(identifier, _, _)| *identifier == field)
+        else {
+            return Err(self.error(field, FieldErrorKind::Missing));
+        };
+
+        match T::read(value) {
+            FieldRead::Value(value) => Ok(Some(value)),
+            FieldRead::Null => Ok(None),
+            FieldRead::WrongType => Err(self.error(
+                field,
+                FieldErrorKind::WrongType {
+                    expected: T::EXPECTED,
+                    found: field_type.to_string(),
+                },
+            )),
+            FieldRead::UnknownValue(value) => {
+                Err(self.error(field, FieldErrorKind::UnknownValue(value)))
+            }
+        }
+    }
+
+    /// Checks that a field exists and is set, whatever its type
+    pub fn require(&self, field: &str) -> Result<(), FieldError> {
+        match self
+            .fields
+            .iter()
+            .find(|(identifier, _, _)| *identifier == field)
+        {
+            None => Err(self.error(field, FieldErrorKind::Missing)),
+            Some((_, _, value)) if is_null(value) => Err(self.error(field, FieldErrorKind::Null)),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn error(&self, field: &str, kind: FieldErrorKind) -> FieldError {
+        FieldError {
+            level: self.level.clone(),
+            entity: self.entity.into(),
+            iid: self.iid.into(),
+            field: field.into(),
+            kind,
+        }
+    }
+}
+
+/// A component read from the fields of an LDtk entity
+pub trait FromFields: Sized {
+    /// Whether entities that cannot work without the component are despawned
+    /// when their fields are invalid, instead of being left half built
+    const REQUIRED: bool = false;
+
+    fn from_fields(fields: &EntityFields) -> Result<Self, FieldError>;
+}
+
+/// Inserts `C`, read from their fields, on the LDtk entities with the `Marker` component
+///
+/// Entities with an invalid field log the error and do not get the component,
+/// they are despawned if it is [`FromFields::REQUIRED`].
+pub fn insert_from_fields<C: FromFields + Component, Marker: Component>(
+    mut commands: Commands,
+    entity_query: Query<(Entity, &EntityInstance), Added<Marker>>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for (entity, entity_instance) in entity_query.iter() {
+        let mut ancestor = entity;
+        let level_iid = loop {
+            if let Ok(level_iid) = level_query.get(ancestor) {
+                break Some(level_iid);
+            }
+            match parent_query.get(ancestor) {
+                Ok(parent) => ancestor = parent.get(),
+                Err(_) => break None,
+            }
+        };
+        let level = level_iid.map_or(UNKNOWN_LEVEL.to_string(), |level_iid| {
+            ldtk_projects
+                .get_single()
+                .ok()
+                .and_then(|handle| ldtk_project_assets.get(handle))
+                .and_then(|ldtk_project| ldtk_project.get_raw_level_by_iid(level_iid.get()))
+                .map_or(level_iid.get().clone(), |level| level.identifier.clone())
+        });
+
+        match C::from_fields(&EntityFields::from_entity_instance(entity_instance, &level)) {
+            Ok(component) => {
+                commands.entity(entity).insert(component);
+            }
+            Err(error) => {
+                error!("{error}");
+                if C::REQUIRED {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Spawner {
+        count: i32,
+        delay: Option<f32>,
+        path: Vec<IVec2>,
+    }
+
+    impl FromFields for Spawner {
+        fn from_fields(fields: &EntityFields) -> Result<Self, FieldError> {
+            Ok(Spawner {
+                count: fields.get("Count")?,
+                delay: fields.get_optional("Delay")?,
+                path: fields.get("Path")?,
+            })
+        }
+    }
+
+    fn read(fields: &[(&str, &str, FieldValue)]) -> Result<Spawner, FieldError> {
+        Spawner::from_fields(&EntityFields::new(
+            "Level_0",
+            "Spawner",
+            "iid",
+            fields
+                .iter()
+                .map(|(identifier, field_type, value)| (*identifier, *field_type, value))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn reads_typed_fields() {
+        let spawner = read(&[
+            ("Count", "Int", FieldValue::Int(Some(3))),
+            ("Delay", "Float", FieldValue::Float(None)),
+            (
+                "Path",
+                "Array<Point>",
+                FieldValue::Points(vec![Some(IVec2::new(1, 2)), Some(IVec2::ZERO)]),
+            ),
+        ]);
+
+        assert_eq!(
+            spawner,
+            Ok(Spawner {
+                count: 3,
+                delay: None,
+                path: vec![IVec2::new(1, 2), IVec2::ZERO],
+            })
+        );
+    }
+
+    #[test]
+    fn errors_name_the_level_entity_and_field() {
+        let error = read(&[("Delay", "Float", FieldValue::Float(Some(1.)))]).unwrap_err();
+
+        assert_eq!(error.kind, FieldErrorKind::Missing);
+        assert_eq!(
+            error.to_string(),
+            "level Level_0, entity Spawner iid: field `Count` is missing"
+        );
+    }
+
+    #[test]
+    fn wrong_types_are_reported_with_the_ldtk_type() {
+        let error = read(&[("Count", "Float", FieldValue::Float(Some(3.)))]).unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            FieldErrorKind::WrongType {
+                expected: "Int",
+                found: "Float".into()
+            }
+        );
+    }
+
+    #[test]
+    fn required_fields_and_array_elements_cannot_be_empty() {
+        let empty_count = read(&[("Count", "Int", FieldValue::Int(None))]).unwrap_err();
+        assert_eq!(empty_count.kind, FieldErrorKind::Null);
+
+        let empty_point = read(&[
+            ("Count", "Int", FieldValue::Int(Some(1))),
+            ("Delay", "Float", FieldValue::Float(None)),
+            ("Path", "Array<Point>", FieldValue::Points(vec![None])),
+        ])
+        .unwrap_err();
+        assert_eq!(empty_point.kind, FieldErrorKind::Null);
+        assert_eq!(empty_point.field, "Path");
+    }
+}
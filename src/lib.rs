@@ -0,0 +1,18 @@
+pub mod boss;
+pub mod character_controller_dynamic;
+pub mod combat;
+pub mod dungeon;
+pub mod helpers;
+pub mod interactable;
+pub mod inventory;
+pub mod layers;
+pub mod ldtk_fields;
+pub mod loot;
+pub mod mob;
+pub mod player;
+pub mod progression;
+pub mod ron_asset;
+pub mod run;
+pub mod settings;
+pub mod simulation;
+pub mod stats;
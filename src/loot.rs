@@ -0,0 +1,345 @@
+//! Loot tables rolled when entities die and pickups collected by the player
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    combat::{apply_damage, despawn_dead, Health},
+    inventory::{Inventory, ItemCatalog, Items},
+    layers::Layer,
+    player::Player,
+    ron_asset::RonAssetPlugin,
+    run::RunRng,
+};
+
+const PICKUP_RADIUS: f32 = 6.;
+/// Maximum distance between a dropped pickup and the entity that dropped it
+const PICKUP_SCATTER: f32 = 12.;
+/// Speed of the pickups attracted by a [`Magnet`]
+const PICKUP_MAGNET_SPEED: f32 = 180.;
+
+const PICKUP_TILESET: &str = "kenney-tiny-dungeon/tilemap_packed.png";
+const PICKUP_TILE_SIZE: f32 = 16.;
+const PICKUP_TILESET_COLUMNS: usize = 12;
+const PICKUP_TILESET_ROWS: usize = 11;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<LootTable>::new(&["loot.ron"]))
+            .init_resource::<PickupSprites>()
+            .add_event::<LootEvent>()
+            .add_systems(
+                Update,
+                (
+                    drop_loot.after(apply_damage).before(despawn_dead),
+                    spawn_loot.after(drop_loot),
+                    attract_pickups,
+                    collect_pickups,
+                ),
+            );
+    }
+}
+
+/// Weighted entries drawn when an entity with a [`LootDrop`] dies, loaded from `.loot.ron` files
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct LootTable {
+    /// Number of draws, the same entry can be drawn several times
+    #[serde(default = "one")]
+    pub rolls: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LootEntry {
+    /// Chances of the entry are its weight divided by the sum of the weights of the table
+    pub weight: u32,
+    /// Nothing drops when `None`
+    #[serde(default)]
+    pub pickup: Option<Pickup>,
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut fastrand::Rng) -> Vec<Pickup> {
+        let total_weight: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total_weight == 0 {
+            return Vec::new();
+        }
+
+        (0..self.rolls)
+            .filter_map(|_| {
+                let mut draw = rng.u32(0..total_weight);
+                self.entries
+                    .iter()
+                    .find(|entry| {
+                        if draw < entry.weight {
+                            true
+                        } else {
+                            draw -= entry.weight;
+                            false
+                        }
+                    })
+                    .and_then(|entry| entry.pickup.clone())
+            })
+            .collect()
+    }
+}
+
+/// Collected by the player on contact
+#[derive(Component, Deserialize, Debug, Clone, PartialEq)]
+pub enum Pickup {
+    /// Restores health points
+    HealthPotion(i32),
+    /// Restores mana points
+    Mana(i32),
+    Coins(u32),
+    /// Item identifier
+    Item(String),
+}
+
+impl Pickup {
+    /// Index of the pickup's sprite in the tileset
+    fn sprite_index(&self) -> usize {
+        match self {
+            Pickup::HealthPotion(_) => 115,
+            Pickup::Mana(_) => 116,
+            Pickup::Coins(_) => 101,
+            Pickup::Item(_) => 104,
+        }
+    }
+}
+
+/// Loot table rolled when the entity dies
+#[derive(Component)]
+pub struct LootDrop(pub Handle<LootTable>);
+
+/// An event sent to roll `table` and scatter its pickups around `position`
+#[derive(Event)]
+pub struct LootEvent {
+    pub table: Handle<LootTable>,
+    pub position: Vec2,
+}
+
+/// Pickups within `radius` fly toward the entity
+#[derive(Component, Default)]
+pub struct Magnet {
+    pub radius: f32,
+}
+
+#[derive(Component)]
+pub struct Mana {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Mana {
+    pub fn new(max: i32) -> Self {
+        Mana { current: max, max }
+    }
+}
+
+impl Default for Mana {
+    fn default() -> Self {
+        Mana::new(0)
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Coins(pub u32);
+
+#[derive(Resource)]
+struct PickupSprites(Handle<TextureAtlas>);
+
+impl FromWorld for PickupSprites {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world.resource::<AssetServer>().load(PICKUP_TILESET);
+        let atlas = TextureAtlas::from_grid(
+            texture,
+            Vec2::splat(PICKUP_TILE_SIZE),
+            PICKUP_TILESET_COLUMNS,
+            PICKUP_TILESET_ROWS,
+            None,
+            None,
+        );
+
+        PickupSprites(world.resource_mut::<Assets<TextureAtlas>>().add(atlas))
+    }
+}
+
+fn drop_loot(
+    mut loot_event_writer: EventWriter<LootEvent>,
+    query: Query<(&LootDrop, &Health, &GlobalTransform), Changed<Health>>,
+) {
+    for (loot_drop, health, transform) in query.iter() {
+        if health.is_dead() {
+            loot_event_writer.send(LootEvent {
+                table: loot_drop.0.clone(),
+                position: transform.translation().truncate(),
+            });
+        }
+    }
+}
+
+/// Tables that are not loaded yet drop nothing
+fn spawn_loot(
+    mut commands: Commands,
+    mut rng: ResMut<RunRng>,
+    mut loot_event_reader: EventReader<LootEvent>,
+    loot_tables: Res<Assets<LootTable>>,
+    pickup_sprites: Res<PickupSprites>,
+) {
+    for event in loot_event_reader.read() {
+        let Some(loot_table) = loot_tables.get(&event.table) else {
+            continue;
+        };
+
+        for pickup in loot_table.roll(&mut rng.0) {
+            let offset = PICKUP_SCATTER
+                * rng.0.f32()
+                * Vec2::from_angle(rng.0.f32() * std::f32::consts::TAU);
+            spawn_pickup(
+                &mut commands,
+                &pickup_sprites,
+                pickup,
+                event.position + offset,
+            );
+        }
+    }
+}
+
+fn spawn_pickup(
+    commands: &mut Commands,
+    pickup_sprites: &PickupSprites,
+    pickup: Pickup,
+    position: Vec2,
+) {
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: pickup_sprites.0.clone(),
+            sprite: TextureAtlasSprite::new(pickup.sprite_index()),
+            transform: Transform::from_translation(position.extend(0.)),
+            ..default()
+        },
+        pickup,
+        Collider::ball(PICKUP_RADIUS),
+        Layer::pickup(),
+        Sensor,
+        RigidBody::Kinematic,
+    ));
+}
+
+fn attract_pickups(
+    magnet_query: Query<(&Magnet, &GlobalTransform)>,
+    mut pickup_query: Query<(&mut LinearVelocity, &GlobalTransform), With<Pickup>>,
+) {
+    for (mut linear_velocity, pickup_transform) in pickup_query.iter_mut() {
+        let position = pickup_transform.translation().truncate();
+        let closest = magnet_query
+            .iter()
+            .map(|(magnet, transform)| (magnet, transform.translation().truncate() - position))
+            .filter(|(magnet, offset)| offset.length() <= magnet.radius)
+            .min_by(|(_, a), (_, b)| a.length().total_cmp(&b.length()));
+
+        linear_velocity.0 = match closest {
+            Some((_, offset)) => PICKUP_MAGNET_SPEED * offset.normalize_or_zero(),
+            None => Vec2::ZERO,
+        };
+    }
+}
+
+/// Items are left on the ground while they do not fit in the player's [`Inventory`]
+#[allow(clippy::type_complexity)]
+fn collect_pickups(
+    mut commands: Commands,
+    collisions: Res<Collisions>,
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut player_query: Query<(&mut Health, &mut Mana, &mut Coins, &mut Inventory), With<Player>>,
+    pickup_query: Query<&Pickup>,
+) {
+    for contacts in collisions
+        .iter()
+        .filter(|contacts| contacts.during_current_frame)
+    {
+        for (player, pickup_entity) in [
+            (contacts.entity1, contacts.entity2),
+            (contacts.entity2, contacts.entity1),
+        ] {
+            let (Ok((mut health, mut mana, mut coins, mut inventory)), Ok(pickup)) = (
+                player_query.get_mut(player),
+                pickup_query.get(pickup_entity),
+            ) else {
+                continue;
+            };
+
+            match pickup {
+                Pickup::HealthPotion(amount) => health.heal(*amount),
+                Pickup::Mana(amount) => mana.current = (mana.current + amount).min(mana.max),
+                Pickup::Coins(amount) => coins.0 += amount,
+                Pickup::Item(item) => {
+                    let max_stack = catalogs
+                        .get(&items.0)
+                        .map_or(1, |catalog| catalog.max_stack(item));
+                    if inventory.add(item, 1, max_stack) > 0 {
+                        continue;
+                    }
+                }
+            }
+            commands.entity(pickup_entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_loot_tables_are_valid() {
+        for entry in std::fs::read_dir("assets/loot").unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            if let Err(error) = ron::from_str::<LootTable>(&content) {
+                panic!("{}: {error}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn rolls_follow_the_weights() {
+        let loot_table = LootTable {
+            rolls: 10_000,
+            entries: vec![
+                LootEntry {
+                    weight: 3,
+                    pickup: Some(Pickup::Coins(1)),
+                },
+                LootEntry {
+                    weight: 1,
+                    pickup: None,
+                },
+            ],
+        };
+
+        let drops = loot_table.roll(&mut fastrand::Rng::with_seed(0));
+
+        assert!(drops.iter().all(|pickup| *pickup == Pickup::Coins(1)));
+        assert!((7_000..8_000).contains(&drops.len()), "{}", drops.len());
+    }
+
+    #[test]
+    fn empty_tables_drop_nothing() {
+        let loot_table = LootTable {
+            rolls: 3,
+            entries: Vec::new(),
+        };
+
+        assert!(loot_table.roll(&mut fastrand::Rng::with_seed(0)).is_empty());
+    }
+}
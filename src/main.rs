@@ -1,41 +1,35 @@
 // #![windows_subsystem = "windows"]
 
-use bevy::{
-    prelude::*,
-    window::{PresentMode, WindowMode},
-};
+use bevy::prelude::*;
 
 use bevy_ecs_ldtk::LdtkPlugin;
 use bevy_xpbd_2d::prelude::*;
 
-use dungeon::DungeonPlugin;
-use mob::MobPlugin;
-use player::PlayerPlugin;
-
-mod character_controller_dynamic;
-mod dungeon;
-mod helpers;
-mod mob;
-mod player;
+use ra_lex_jin::{
+    boss::BossPlugin,
+    combat::CombatPlugin,
+    dungeon::DungeonPlugin,
+    helpers,
+    interactable::InteractablePlugin,
+    inventory::InventoryPlugin,
+    loot::LootPlugin,
+    mob::MobPlugin,
+    player::PlayerPlugin,
+    progression::ProgressionPlugin,
+    run::RunPlugin,
+    settings::{Settings, SettingsPlugin, SETTINGS_PATH},
+    stats::StatsPlugin,
+};
 
 fn main() {
     let mut app = App::new();
+    let settings = Settings::load(SETTINGS_PATH);
 
     app.add_plugins((
         DefaultPlugins
             .set(ImagePlugin::default_nearest())
             .set(WindowPlugin {
-                primary_window: Some(Window {
-                    title: "Ra Lex Jin".into(),
-                    resolution: (1600., 900.).into(),
-                    present_mode: PresentMode::AutoVsync,
-                    mode: WindowMode::Windowed,
-                    // Tells wasm to resize the window according to the available canvas
-                    fit_canvas_to_parent: true,
-                    // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
-                    prevent_default_event_handling: false,
-                    ..default()
-                }),
+                primary_window: Some(settings.primary_window()),
                 ..default()
             }),
         // bevy_ecs_ldtk
@@ -43,13 +37,24 @@ fn main() {
         // bevy_xpbd_2d
         PhysicsPlugins::default(),
         // Game plugins
-        PlayerPlugin,
-        DungeonPlugin,
-        MobPlugin,
+        (
+            PlayerPlugin,
+            DungeonPlugin::default(),
+            MobPlugin,
+            BossPlugin,
+            InteractablePlugin,
+            CombatPlugin,
+            LootPlugin,
+            InventoryPlugin,
+            RunPlugin,
+            StatsPlugin,
+            ProgressionPlugin,
+        ),
         // Limit FPS
         bevy_framepace::FramepacePlugin,
+        SettingsPlugin,
     ))
-    .add_systems(Startup, maximize_window)
+    .insert_resource(settings)
     .insert_resource(Msaa::Off)
     .insert_resource(Gravity::ZERO);
 
@@ -61,8 +66,3 @@ fn main() {
 
     app.run();
 }
-
-fn maximize_window(mut window_query: Query<&mut Window>) {
-    let mut window = window_query.single_mut();
-    window.set_maximized(true);
-}
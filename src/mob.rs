@@ -1,18 +1,139 @@
-use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*};
 use bevy_xpbd_2d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    character_controller_dynamic::Stunned,
+    combat::{
+        bullet_pattern::Shooter,
+        hitbox::*,
+        status_effect::{Resistances, StatusEffects, StatusKind},
+        Attack, Health,
+    },
+    layers::Layer,
+    ldtk_fields::{
+        insert_from_fields, read_enum, EntityFields, FieldError, FieldRead, FieldType, FromFields,
+    },
+    loot::LootDrop,
+    player::Player,
+    progression::ExperienceReward,
+    ron_asset::RonAssetPlugin,
+    stats::{Stat, Stats},
+};
+
+/// File defining the archetype of each [`MobKind`], relative to the assets folder
+pub const MOB_CATALOG_PATH: &str = "mobs/catalog.mobs.ron";
+pub const MOB_DAMPING: f32 = 8.;
+/// Delay between two contact damages dealt by the same mob to the player
+pub const MOB_CONTACT_DAMAGE_DELAY: Duration = Duration::from_secs(1);
+/// Mobs stay still while the player is farther than this
+pub const MOB_AGGRO_RANGE: f32 = 160.;
+/// Distance around the preferred distance of a kiting mob within which it stays still
+const MOB_KITE_TOLERANCE: f32 = 8.;
 
 pub struct MobPlugin;
 
 impl Plugin for MobPlugin {
     fn build(&self, app: &mut App) {
-        app.register_ldtk_entity::<MobBundle>("Mob");
+        app.add_plugins(RonAssetPlugin::<MobCatalog>::new(&["mobs.ron"]))
+            .init_resource::<MobArchetypes>()
+            .register_ldtk_entity::<MobBundle>("Mob")
+            .add_systems(
+                Update,
+                (
+                    insert_from_fields::<MobFields, Mob>,
+                    assemble_mobs,
+                    move_mobs,
+                ),
+            );
+    }
+}
+
+/// Kind of a mob, selected by the `Kind` enum field of the `Mob` LDtk entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum MobKind {
+    Slime,
+    Ghost,
+    Archer,
+    Bat,
+}
+
+impl MobKind {
+    pub const ALL: [MobKind; 4] = [
+        MobKind::Slime,
+        MobKind::Ghost,
+        MobKind::Archer,
+        MobKind::Bat,
+    ];
+}
+
+impl FieldType for MobKind {
+    const EXPECTED: &'static str = "LocalEnum.MobKind";
+
+    fn read(value: &FieldValue) -> FieldRead<Self> {
+        read_enum(value, |value| {
+            MobKind::ALL
+                .into_iter()
+                .find(|kind| format!("{kind:?}") == value)
+        })
+    }
+}
+
+/// Archetypes of the mobs by kind, loaded from [`MOB_CATALOG_PATH`]
+#[derive(Asset, TypePath, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct MobCatalog(pub HashMap<MobKind, MobArchetype>);
+
+/// Everything that differs between two kinds of mobs
+#[derive(Deserialize, Debug, Clone)]
+pub struct MobArchetype {
+    /// Index of the mob's sprite in the tileset
+    pub sprite: usize,
+    pub health: i32,
+    /// Top speed in pixels per second
+    pub speed: f32,
+    pub behavior: MobBehavior,
+    pub contact_damage: i32,
+    /// Bullet pattern fired at the player, relative to the assets folder
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub resistances: HashMap<StatusKind, f32>,
+    /// Loot table rolled when the mob dies, relative to the assets folder
+    #[serde(default)]
+    pub loot_table: Option<String>,
+    pub experience: u32,
+}
+
+/// How a mob moves once the player is within [`MOB_AGGRO_RANGE`]
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MobBehavior {
+    Still,
+    /// Walk toward the player
+    Chase,
+    /// Keep `distance` pixels away from the player
+    Kite {
+        distance: f32,
+    },
+}
+
+/// Handle keeping the [`MobCatalog`] loaded
+#[derive(Resource)]
+pub struct MobArchetypes(pub Handle<MobCatalog>);
+
+impl FromWorld for MobArchetypes {
+    fn from_world(world: &mut World) -> Self {
+        MobArchetypes(world.resource::<AssetServer>().load(MOB_CATALOG_PATH))
     }
 }
 
 #[derive(Default, Component)]
 pub struct Mob;
 
+/// Health, attacks and behavior are added from the archetype by [`assemble_mobs`]
 #[derive(Default, Bundle, LdtkEntity)]
 pub struct MobBundle {
     pub mob: Mob,
@@ -20,6 +141,129 @@ pub struct MobBundle {
     pub sprite_sheet_bundle: SpriteSheetBundle,
     #[with(mob_hitbox)]
     pub collider: Collider,
+    pub rigid_body: RigidBody,
+    #[with(mob_locked_axes)]
+    pub locked_axes: LockedAxes,
+    #[with(mob_damping)]
+    pub linear_damping: LinearDamping,
+    #[with(mob_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    pub status_effects: StatusEffects,
+}
+
+/// Custom fields of the `Mob` LDtk entity
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct MobFields {
+    pub kind: MobKind,
+    /// Fired at the player instead of the archetype's pattern when set
+    pub bullet_pattern: Option<String>,
+    /// Rolled when the mob dies instead of the archetype's loot table when set
+    pub loot_table: Option<String>,
+}
+
+/// Mobs are only assembled once their kind is known
+impl FromFields for MobFields {
+    const REQUIRED: bool = true;
+
+    fn from_fields(fields: &EntityFields) -> Result<Self, FieldError> {
+        Ok(MobFields {
+            kind: fields.get("Kind")?,
+            bullet_pattern: fields.get_optional("BulletPattern")?,
+            loot_table: fields.get_optional("LootTable")?,
+        })
+    }
+}
+
+/// Gives mobs the components of their archetype once the catalog is loaded,
+/// mobs can only be hit once they have them
+#[allow(clippy::type_complexity)]
+fn assemble_mobs(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    archetypes: Res<MobArchetypes>,
+    catalogs: Res<Assets<MobCatalog>>,
+    mut mob_query: Query<
+        (Entity, &MobFields, &mut TextureAtlasSprite),
+        (With<Mob>, Without<MobBehavior>),
+    >,
+) {
+    let Some(catalog) = catalogs.get(&archetypes.0) else {
+        return;
+    };
+
+    for (entity, fields, mut sprite) in mob_query.iter_mut() {
+        let Some(archetype) = catalog.0.get(&fields.kind) else {
+            error!(
+                "no archetype for the {:?} mobs in {MOB_CATALOG_PATH}",
+                fields.kind
+            );
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        sprite.index = archetype.sprite;
+        let mut mob = commands.entity(entity);
+        mob.insert((
+            archetype.behavior,
+            Health::new(archetype.health),
+            Hurtbox::new(Team::Enemy),
+            Hitbox::new(Team::Enemy).with_rehit_delay(MOB_CONTACT_DAMAGE_DELAY),
+            Attack::new(archetype.contact_damage),
+            // The damping slows mobs down until they reach their top speed
+            Stats::new([(Stat::MoveAcceleration, archetype.speed * MOB_DAMPING)]),
+            Resistances(archetype.resistances.clone()),
+            ExperienceReward(archetype.experience),
+        ));
+        if let Some(pattern) = fields
+            .bullet_pattern
+            .as_ref()
+            .or(archetype.pattern.as_ref())
+        {
+            mob.insert(Shooter::new(Team::Enemy, asset_server.load(pattern)));
+        }
+        if let Some(loot_table) = fields.loot_table.as_ref().or(archetype.loot_table.as_ref()) {
+            mob.insert(LootDrop(asset_server.load(loot_table)));
+        }
+    }
+}
+
+/// Accelerates mobs near the player according to their [`MobBehavior`]
+fn move_mobs(
+    time: Res<Time>,
+    mut mob_query: Query<
+        (&mut LinearVelocity, &GlobalTransform, &MobBehavior, &Stats),
+        (With<Mob>, Without<Stunned>),
+    >,
+    player_query: Query<&GlobalTransform, With<Player>>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    for (mut linear_velocity, transform, behavior, stats) in mob_query.iter_mut() {
+        let offset = (player_transform.translation() - transform.translation()).truncate();
+        let distance = offset.length();
+        if distance > MOB_AGGRO_RANGE {
+            continue;
+        }
+
+        let direction = match *behavior {
+            MobBehavior::Still => continue,
+            MobBehavior::Chase => offset.normalize_or_zero(),
+            MobBehavior::Kite {
+                distance: preferred,
+            } => {
+                if distance < preferred - MOB_KITE_TOLERANCE {
+                    -offset.normalize_or_zero()
+                } else if distance > preferred + MOB_KITE_TOLERANCE {
+                    offset.normalize_or_zero()
+                } else {
+                    continue;
+                }
+            }
+        };
+        linear_velocity.0 += direction * stats.get(Stat::MoveAcceleration) * time.delta_seconds();
+    }
 }
 
 fn mob_hitbox(entity_instance: &EntityInstance) -> Collider {
@@ -28,3 +272,89 @@ fn mob_hitbox(entity_instance: &EntityInstance) -> Collider {
 
     Collider::cuboid(width, height)
 }
+
+fn mob_locked_axes(_: &EntityInstance) -> LockedAxes {
+    LockedAxes::ROTATION_LOCKED
+}
+
+/// Slows down mobs after a knockback
+fn mob_damping(_: &EntityInstance) -> LinearDamping {
+    LinearDamping(MOB_DAMPING)
+}
+
+fn mob_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Layer::mob()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::LdtkJson;
+
+    use super::*;
+
+    #[test]
+    fn bundled_catalog_has_every_kind() {
+        let content = std::fs::read_to_string(format!("assets/{MOB_CATALOG_PATH}")).unwrap();
+        let catalog: MobCatalog = ron::from_str(&content).unwrap();
+
+        for kind in MobKind::ALL {
+            let archetype = catalog.0.get(&kind);
+            assert!(archetype.is_some(), "{kind:?} should have an archetype");
+            assert!(archetype.unwrap().health > 0, "{kind:?} should have health");
+        }
+    }
+
+    /// Slime of Room_0 in the test map
+    fn room_0_mob() -> EntityInstance {
+        let content = std::fs::read_to_string("assets/tests/room_0.ldtk").unwrap();
+        let project: LdtkJson = serde_json::from_str(&content).unwrap();
+        project.levels[0]
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| &layer.entity_instances)
+            .find(|entity| entity.identifier == "Mob")
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn mobs_of_unknown_kinds_are_despawned() {
+        let mut app = App::new();
+        app.init_resource::<Assets<LdtkProject>>()
+            .add_systems(Update, insert_from_fields::<MobFields, Mob>);
+        let mut entity_instance = room_0_mob();
+        entity_instance
+            .field_instances
+            .iter_mut()
+            .find(|field| field.identifier == "Kind")
+            .unwrap()
+            .value = FieldValue::Enum(Some("Dragon".into()));
+        let mob = app.world.spawn((Mob, entity_instance)).id();
+        let slime = app.world.spawn((Mob, room_0_mob())).id();
+
+        app.update();
+
+        assert!(app.world.get_entity(mob).is_none());
+        assert_eq!(
+            app.world.get::<MobFields>(slime).map(|fields| fields.kind),
+            Some(MobKind::Slime)
+        );
+    }
+
+    #[test]
+    fn kinds_are_read_from_ldtk_enum_values() {
+        assert_eq!(
+            MobKind::read(&FieldValue::Enum(Some("Archer".into()))),
+            FieldRead::Value(MobKind::Archer)
+        );
+        assert_eq!(
+            MobKind::read(&FieldValue::Enum(Some("Dragon".into()))),
+            FieldRead::UnknownValue("Dragon".into())
+        );
+        assert_eq!(
+            MobKind::read(&FieldValue::FilePath(Some("Archer".into()))),
+            FieldRead::WrongType
+        );
+    }
+}
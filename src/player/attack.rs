@@ -1,96 +1,287 @@
+use std::time::Duration;
+
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_xpbd_2d::prelude::*;
 
-use crate::{dungeon::collisions::*, mob::Mob};
+use crate::{
+    combat::{
+        bullet_pattern::{spawn_projectile, VolleyShape},
+        status_effect::*,
+        Attack, FriendlyFire, Knockback, Lifetime,
+    },
+    inventory::{Equipment, ItemCatalog, Items, MeleeSpec, WeaponAttack},
+    layers::Layer,
+    stats::{Stat, Stats},
+};
 
 use super::*;
 
-// pub enum DamageType {
-//     Physical,
-//     Magical,
-//     True,
-// }
+const FIREBALL_RADIUS: f32 = 10.;
+/// Distance from the player at which projectiles spawn, so that they do not overlap their caster
+const PROJECTILE_SPAWN_DISTANCE: f32 = 24.;
+/// Angle in degrees between the projectiles of an attack firing several of them
+const PROJECTILE_SPREAD: f32 = 12.;
+/// Burn damage per second
+const FIREBALL_BURN: f32 = 3.;
+const FIREBALL_BURN_DURATION: Duration = Duration::from_secs(3);
 
-#[derive(Component)]
-pub struct Attack {
-    // pub damage_type: DamageType,
-    pub amount: i32,
-}
+const MELEE_DAMAGE: i32 = 15;
+/// Size of the swing along and across the aim direction
+const MELEE_SIZE: Vec2 = Vec2::new(16., 28.);
+/// Distance from the player to the center of the swing
+const MELEE_REACH: f32 = 16.;
+const MELEE_DURATION: Duration = Duration::from_millis(150);
+const MELEE_KNOCKBACK: f32 = 250.;
+const MELEE_STUN_DURATION: Duration = Duration::from_millis(300);
 
-impl Attack {
-    pub fn new(amount: i32) -> Self {
-        Attack { amount }
-    }
+/// An event sent for an attack input, aiming toward a world position.
+#[derive(Event)]
+pub enum AttackAction {
+    /// Attack of the equipped weapon, a fireball when no weapon is equipped
+    Weapon(Vec2),
+    Melee(Vec2),
 }
 
-#[derive(Component)]
-pub struct Projectile;
-
-pub fn player_attack(
-    mut commands: Commands,
+/// Sends [`AttackAction`] events based on mouse input.
+pub fn mouse_attack_input(
+    mut attack_event_writer: EventWriter<AttackAction>,
     mouse_button_input: Res<Input<MouseButton>>,
-    player_query: Query<(&Transform, &LinearVelocity), With<Player>>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
-    asset_server: Res<AssetServer>,
 ) {
-    if mouse_button_input.just_pressed(MouseButton::Left) {
-        let (camera, camera_transform) = camera_query.single();
-        if let Some(cursor_position) = primary_window
-            .single()
+    let weapon = mouse_button_input.just_pressed(MouseButton::Left);
+    let melee = mouse_button_input.just_pressed(MouseButton::Right);
+
+    if weapon || melee {
+        let (Ok(window), Ok((camera, camera_transform))) =
+            (primary_window.get_single(), camera_query.get_single())
+        else {
+            return;
+        };
+
+        if let Some(cursor_position) = window
             .cursor_position()
             .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
             .map(|ray| ray.origin.truncate())
         {
-            let (player_transform, player_linear_velocity) = player_query.single();
-            let player_translation = player_transform.translation;
-            let player_2d_translation = player_translation.truncate();
-            let direction = match (cursor_position - player_2d_translation).try_normalize() {
-                Some(direction) => direction,
-                None => Vec2::X,
-            };
-            let velocity = LinearVelocity {
-                0: 100. * direction + player_linear_velocity.0,
-            };
-
-            let mut fireball = commands.spawn_empty();
-
-            fireball
-                .insert((
-                    Attack::new(10),
-                    Collider::ball(10.),
-                    Sensor,
-                    RigidBody::Dynamic,
-                    velocity,
-                    LockedAxes::ROTATION_LOCKED,
-                    SpriteBundle {
-                        texture: asset_server.load("fireball.png"),
-                        ..default()
-                    },
-                    Projectile,
-                ))
-                .insert(TransformBundle::from_transform(*player_transform));
+            if weapon {
+                attack_event_writer.send(AttackAction::Weapon(cursor_position));
+            }
+            if melee {
+                attack_event_writer.send(AttackAction::Melee(cursor_position));
+            }
         }
     }
 }
 
-pub fn fireball_collisions(
+/// Swing performed on [`AttackAction::Melee`], whatever the equipped weapon
+fn base_melee() -> MeleeSpec {
+    MeleeSpec {
+        damage: MELEE_DAMAGE,
+        knockback: MELEE_KNOCKBACK,
+        reach: MELEE_REACH,
+        size: (MELEE_SIZE.x, MELEE_SIZE.y),
+        duration: MELEE_DURATION.as_secs_f32(),
+        stun: MELEE_STUN_DURATION.as_secs_f32(),
+    }
+}
+
+/// Delay before the next weapon attack, derived from [`Stat::FireRate`]
+#[derive(Component, Default)]
+pub struct WeaponCooldown(Timer);
+
+/// Responds to [`AttackAction`] events by spawning projectiles and melee swings from the player.
+///
+/// Damage and projectile speed are taken from the player's [`Stats`], whose modifiers also
+/// apply to the values of the equipped weapon.
+#[allow(clippy::type_complexity)]
+pub fn player_attack(
     mut commands: Commands,
-    mut collision_event_reader: EventReader<CollisionStarted>,
-    wall_query: Query<With<Terrain>>,
-    projectile_query: Query<With<Projectile>>,
-    mob_query: Query<With<Mob>>,
+    time: Res<Time>,
+    mut attack_event_reader: EventReader<AttackAction>,
+    mut player_query: Query<
+        (
+            Entity,
+            &Transform,
+            &LinearVelocity,
+            &Equipment,
+            &Stats,
+            &mut WeaponCooldown,
+        ),
+        With<Player>,
+    >,
+    asset_server: Res<AssetServer>,
+    items: Res<Items>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    friendly_fire: Res<FriendlyFire>,
 ) {
-    for &CollisionStarted(entity1, entity2) in collision_event_reader.read() {
-        for (entity1, entity2) in [(entity1, entity2), (entity2, entity1)] {
-            if projectile_query.contains(entity1) {
-                if wall_query.contains(entity2) {
-                    commands.entity(entity1).despawn_recursive();
-                } else if mob_query.contains(entity2) {
-                    commands.entity(entity1).despawn_recursive();
-                    commands.entity(entity2).despawn_recursive();
+    let Ok((
+        player_entity,
+        player_transform,
+        player_linear_velocity,
+        equipment,
+        stats,
+        mut weapon_cooldown,
+    )) = player_query.get_single_mut()
+    else {
+        return;
+    };
+    weapon_cooldown.0.tick(time.delta());
+
+    for event in attack_event_reader.read() {
+        let aim = |target: Vec2| {
+            let player_2d_translation = player_transform.translation.truncate();
+            match (target - player_2d_translation).try_normalize() {
+                Some(direction) => direction,
+                None => Vec2::X,
+            }
+        };
+
+        match *event {
+            AttackAction::Weapon(target) => {
+                if !weapon_cooldown.0.finished() {
+                    continue;
+                }
+                let fire_rate = stats.get(Stat::FireRate);
+                if fire_rate > 0. {
+                    weapon_cooldown.0 = Timer::from_seconds(fire_rate.recip(), TimerMode::Once);
+                }
+
+                let direction = aim(target);
+                let weapon = catalogs
+                    .get(&items.0)
+                    .and_then(|catalog| equipment.weapon(catalog));
+
+                let count = (stats.get(Stat::ProjectileCount).round() as u32).max(1);
+                let directions = VolleyShape::Spread {
+                    count,
+                    angle: PROJECTILE_SPREAD * (count - 1) as f32,
+                }
+                .directions(direction, 0);
+                let pierce = stats.get(Stat::Pierce).round() as u32;
+
+                match weapon {
+                    Some(WeaponAttack::Projectile(projectile)) => {
+                        let mut projectile = projectile.clone();
+                        projectile.damage =
+                            stats.apply(Stat::Damage, projectile.damage as f32).round() as i32;
+                        projectile.speed = stats.apply(Stat::ProjectileSpeed, projectile.speed);
+
+                        for direction in directions {
+                            let projectile = spawn_projectile(
+                                &mut commands,
+                                &asset_server,
+                                &projectile,
+                                Team::Player,
+                                player_entity,
+                                friendly_fire.0,
+                                player_transform.translation.truncate()
+                                    + PROJECTILE_SPAWN_DISTANCE * direction,
+                                direction,
+                            );
+                            if pierce > 0 {
+                                commands.entity(projectile).insert(Pierce(pierce));
+                            }
+                        }
+                    }
+                    Some(WeaponAttack::Melee(melee)) => spawn_swing(
+                        &mut commands,
+                        melee,
+                        stats,
+                        player_entity,
+                        player_transform,
+                        direction,
+                        friendly_fire.0,
+                    ),
+                    None => {
+                        for direction in directions {
+                            let velocity = LinearVelocity(
+                                stats.get(Stat::ProjectileSpeed) * direction
+                                    + player_linear_velocity.0,
+                            );
+
+                            let mut fireball = commands.spawn_empty();
+
+                            fireball
+                                .insert((
+                                    Attack::new(stats.get(Stat::Damage).round() as i32),
+                                    Hitbox::new(Team::Player).with_owner(player_entity),
+                                    OnHitEffects(vec![StatusEffect::new(
+                                        StatusKind::Burn,
+                                        FIREBALL_BURN,
+                                        FIREBALL_BURN_DURATION,
+                                        Stacking::Refresh,
+                                    )]),
+                                    Collider::ball(FIREBALL_RADIUS),
+                                    Layer::player_projectile(friendly_fire.0),
+                                    Sensor,
+                                    RigidBody::Dynamic,
+                                    velocity,
+                                    LockedAxes::ROTATION_LOCKED,
+                                    SpriteBundle {
+                                        texture: asset_server.load("fireball.png"),
+                                        ..default()
+                                    },
+                                    Projectile,
+                                ))
+                                .insert(TransformBundle::from_transform(
+                                    player_transform.with_translation(
+                                        player_transform.translation
+                                            + (PROJECTILE_SPAWN_DISTANCE * direction).extend(0.),
+                                    ),
+                                ));
+                            if pierce > 0 {
+                                fireball.insert(Pierce(pierce));
+                            }
+                        }
+                    }
                 }
             }
+            AttackAction::Melee(target) => spawn_swing(
+                &mut commands,
+                &base_melee(),
+                stats,
+                player_entity,
+                player_transform,
+                aim(target),
+                friendly_fire.0,
+            ),
         }
     }
 }
+
+fn spawn_swing(
+    commands: &mut Commands,
+    melee: &MeleeSpec,
+    stats: &Stats,
+    player_entity: Entity,
+    player_transform: &Transform,
+    direction: Vec2,
+    friendly_fire: bool,
+) {
+    let mut swing = commands.spawn((
+        Attack::new(stats.apply(Stat::Damage, melee.damage as f32).round() as i32),
+        Hitbox::new(Team::Player).with_owner(player_entity),
+        Knockback(melee.knockback),
+        Deflector,
+        Lifetime::new(Duration::from_secs_f32(melee.duration)),
+        Collider::cuboid(melee.size.0, melee.size.1),
+        Layer::player_projectile(friendly_fire),
+        Sensor,
+        TransformBundle::from_transform(
+            Transform::from_translation(
+                player_transform.translation + (melee.reach * direction).extend(0.),
+            )
+            .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
+        ),
+    ));
+
+    if melee.stun > 0. {
+        swing.insert(OnHitEffects(vec![StatusEffect::new(
+            StatusKind::Stun,
+            0.,
+            Duration::from_secs_f32(melee.stun),
+            Stacking::Refresh,
+        )]));
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_xpbd_2d::prelude::*;
+
+use crate::{
+    dungeon::rooms::{CurrentRoom, RoomEntered, RoomExited},
+    run::{pause_time, unpause_time, RunState},
+    settings::{RoomTransitionMode, Settings},
+};
+
+use super::Player;
+
+/// Duration of the camera pan between two rooms in [`RoomTransitionMode::Pan`]
+pub const ROOM_PAN_DURATION: Duration = Duration::from_millis(600);
+
+pub(super) struct PlayerCameraPlugin;
+
+impl Plugin for PlayerCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                add_player_camera,
+                apply_camera_zoom.run_if(resource_changed::<Settings>()),
+                start_room_transition.run_if(in_state(RunState::Playing)),
+                pan_camera.run_if(in_state(RunState::RoomTransition)),
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            keep_camera_in_room
+                .run_if(in_state(RunState::Playing).and_then(not(resource_exists::<CameraPan>())))
+                .after(PhysicsSet::Sync)
+                .before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(OnEnter(RunState::RoomTransition), pause_time)
+        .add_systems(OnExit(RunState::RoomTransition), unpause_time);
+    }
+}
+
+#[derive(Default, Component)]
+pub struct PlayerCamera;
+
+/// Camera movement from the room the player left to the one it entered
+#[derive(Resource)]
+struct CameraPan {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+}
+
+/// Add 2D camera following player
+fn add_player_camera(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    settings: Res<Settings>,
+) {
+    if let Ok(player_entity) = player_query.get_single() {
+        let mut camera_2d = Camera2dBundle::default();
+        camera_2d.projection.scale = settings.camera_zoom;
+
+        commands.entity(player_entity).with_children(|parent| {
+            parent.spawn((camera_2d, PlayerCamera));
+        });
+    }
+}
+
+fn apply_camera_zoom(
+    settings: Res<Settings>,
+    mut projection_query: Query<&mut OrthographicProjection, With<PlayerCamera>>,
+) {
+    for mut projection in projection_query.iter_mut() {
+        projection.scale = settings.camera_zoom;
+    }
+}
+
+/// Center of the camera closest to the player showing only the room within `bounds`,
+/// rooms smaller than the view are centered on it
+fn room_camera_position(player: Vec2, bounds: Rect, view_size: Vec2) -> Vec2 {
+    let min = bounds.min + view_size / 2.;
+    let max = bounds.max - view_size / 2.;
+    let center = bounds.center();
+    Vec2::new(
+        if min.x < max.x {
+            player.x.clamp(min.x, max.x)
+        } else {
+            center.x
+        },
+        if min.y < max.y {
+            player.y.clamp(min.y, max.y)
+        } else {
+            center.y
+        },
+    )
+}
+
+/// Offsets the camera from the player so that it does not show the neighbors of the room
+fn keep_camera_in_room(
+    settings: Res<Settings>,
+    current_room: Res<CurrentRoom>,
+    player_query: Query<&Position, With<Player>>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<PlayerCamera>>,
+) {
+    let (Ok(player_position), Ok((mut camera_transform, projection))) =
+        (player_query.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+
+    let offset = match settings.room_transition {
+        RoomTransitionMode::Pan if current_room.level_iid().is_some() => {
+            room_camera_position(
+                player_position.0,
+                current_room.bounds(),
+                projection.area.size(),
+            ) - player_position.0
+        }
+        _ => Vec2::ZERO,
+    };
+    if camera_transform.translation.truncate() != offset {
+        camera_transform.translation = offset.extend(camera_transform.translation.z);
+    }
+}
+
+/// Freezes the game while the camera pans to the room the player entered
+#[allow(clippy::too_many_arguments)]
+fn start_room_transition(
+    mut commands: Commands,
+    mut room_entered_reader: EventReader<RoomEntered>,
+    mut room_exited_reader: EventReader<RoomExited>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+    settings: Res<Settings>,
+    current_room: Res<CurrentRoom>,
+    player_query: Query<&Position, With<Player>>,
+    camera_query: Query<(&GlobalTransform, &OrthographicProjection), With<PlayerCamera>>,
+) {
+    let entered = room_entered_reader.read().count() > 0;
+    // The first room the player spawns in has nothing to pan from
+    let exited = room_exited_reader.read().count() > 0;
+    if !entered || !exited || settings.room_transition != RoomTransitionMode::Pan {
+        return;
+    }
+    let (Ok(player_position), Ok((camera_transform, projection))) =
+        (player_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+
+    commands.insert_resource(CameraPan {
+        from: camera_transform.translation().truncate(),
+        to: room_camera_position(
+            player_position.0,
+            current_room.bounds(),
+            projection.area.size(),
+        ),
+        timer: Timer::new(ROOM_PAN_DURATION, TimerMode::Once),
+    });
+    next_run_state.set(RunState::RoomTransition);
+}
+
+/// Moves the camera along the [`CameraPan`] in real time since gameplay time is paused
+fn pan_camera(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    pan: Option<ResMut<CameraPan>>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+    player_query: Query<&Position, With<Player>>,
+    mut camera_query: Query<&mut Transform, With<PlayerCamera>>,
+) {
+    let Some(mut pan) = pan else {
+        next_run_state.set(RunState::Playing);
+        return;
+    };
+    let (Ok(player_position), Ok(mut camera_transform)) =
+        (player_query.get_single(), camera_query.get_single_mut())
+    else {
+        return;
+    };
+
+    pan.timer.tick(time.delta());
+    let position = pan.from.lerp(pan.to, pan.timer.percent());
+    camera_transform.translation =
+        (position - player_position.0).extend(camera_transform.translation.z);
+
+    if pan.timer.finished() {
+        commands.remove_resource::<CameraPan>();
+        next_run_state.set(RunState::Playing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_stays_within_rooms_larger_than_the_view() {
+        let bounds = Rect::new(0., 0., 400., 300.);
+        let view_size = Vec2::new(200., 100.);
+
+        assert_eq!(
+            room_camera_position(Vec2::new(150., 150.), bounds, view_size),
+            Vec2::new(150., 150.)
+        );
+        assert_eq!(
+            room_camera_position(Vec2::new(10., 290.), bounds, view_size),
+            Vec2::new(100., 250.)
+        );
+    }
+
+    #[test]
+    fn camera_is_centered_on_rooms_smaller_than_the_view() {
+        let bounds = Rect::new(0., 0., 400., 80.);
+        let view_size = Vec2::new(200., 100.);
+
+        assert_eq!(
+            room_camera_position(Vec2::new(390., 10.), bounds, view_size),
+            Vec2::new(300., 40.)
+        );
+    }
+}
@@ -1,28 +1,63 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use bevy_xpbd_2d::prelude::*;
 
-use crate::character_controller_dynamic::*;
+use crate::{
+    character_controller_dynamic::*,
+    combat::{apply_damage, despawn_dead, hitbox::*, status_effect::StatusEffects, Health},
+    dungeon::hazards::SafeSpot,
+    inventory::{ui::inventory_open, Equipment, Inventory},
+    layers::Layer,
+    loot::{Coins, Magnet, Mana},
+    progression::Experience,
+    run::RunState,
+    stats::{Stat, Stats},
+};
 
-use self::attack::*;
+use self::{attack::*, camera::*};
 
-mod attack;
+pub mod attack;
+pub mod camera;
 
 pub const PLAYER_ACCELERATION: f32 = 2_000.;
 pub const PLAYER_DAMPING: f32 = 0.9;
+pub const PLAYER_DASH_SPEED: f32 = 350.;
+pub const PLAYER_DASH_DURATION: Duration = Duration::from_millis(150);
+pub const PLAYER_DASH_INVULNERABILITY: Duration = Duration::from_millis(250);
+pub const PLAYER_DASH_COOLDOWN: Duration = Duration::from_millis(800);
+pub const PLAYER_HEALTH: i32 = 100;
+/// Damage of the attacks without weapon
+pub const PLAYER_DAMAGE: f32 = 10.;
+/// Weapon attacks per second
+pub const PLAYER_FIRE_RATE: f32 = 4.;
+/// Speed of the projectiles without weapon
+pub const PLAYER_PROJECTILE_SPEED: f32 = 100.;
+pub const PLAYER_MANA: i32 = 50;
+/// Distance from which pickups fly toward the player
+pub const PLAYER_MAGNET_RADIUS: f32 = 40.;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(CharacterControllerPlugin)
+        app.add_plugins((CharacterControllerPlugin, PlayerCameraPlugin))
+            .add_event::<AttackAction>()
             .add_systems(
                 Update,
                 (
-                    add_player_camera,
-                    level_selection_follow_player,
+                    mouse_attack_input
+                        .run_if(in_state(RunState::Playing).and_then(not(inventory_open))),
                     player_attack,
-                    fireball_collisions,
-                ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                lose_run_on_player_death
+                    .after(apply_damage)
+                    .before(despawn_dead),
             )
             .register_ldtk_entity::<PlayerBundle>("Player");
     }
@@ -40,55 +75,77 @@ pub struct PlayerBundle {
     pub worldly: Worldly,
     #[from_entity_instance]
     pub character_controller: CharacterControllerBundle,
+    #[with(player_dash_ability)]
+    pub dash_ability: DashAbility,
+    #[with(player_health)]
+    pub health: Health,
+    #[with(player_collision_layers)]
+    pub collision_layers: CollisionLayers,
+    #[with(player_hurtbox)]
+    pub hurtbox: Hurtbox,
+    pub status_effects: StatusEffects,
+    #[with(player_mana)]
+    pub mana: Mana,
+    pub coins: Coins,
+    pub inventory: Inventory,
+    pub equipment: Equipment,
+    #[with(player_stats)]
+    pub stats: Stats,
+    pub weapon_cooldown: WeaponCooldown,
+    pub experience: Experience,
+    #[with(player_magnet)]
+    pub magnet: Magnet,
+    pub safe_spot: SafeSpot,
 }
 
-#[derive(Default, Component)]
-pub struct PlayerCamera;
+fn lose_run_on_player_death(
+    player_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    if player_query.iter().any(Health::is_dead) {
+        next_run_state.set(RunState::Defeat);
+    }
+}
 
-/// Add 2D camera following player
-fn add_player_camera(mut commands: Commands, player_query: Query<Entity, Added<Player>>) {
-    if let Ok(player_entity) = player_query.get_single() {
-        let mut camera_2d = Camera2dBundle::default();
-        camera_2d.projection.scale = 0.35;
+fn player_dash_ability(_: &EntityInstance) -> DashAbility {
+    DashAbility::new(
+        PLAYER_DASH_SPEED,
+        PLAYER_DASH_DURATION,
+        PLAYER_DASH_INVULNERABILITY,
+        PLAYER_DASH_COOLDOWN,
+    )
+}
 
-        commands.entity(player_entity).with_children(|parent| {
-            parent.spawn((camera_2d, PlayerCamera));
-        });
-    }
+fn player_health(_: &EntityInstance) -> Health {
+    Health::new(PLAYER_HEALTH)
 }
 
-/// Load and unload rooms when player change room
-fn level_selection_follow_player(
-    players: Query<&GlobalTransform, With<Player>>,
-    levels: Query<(&LevelIid, &GlobalTransform)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
-    mut level_selection: ResMut<LevelSelection>,
-) {
-    if let Ok(player_transform) = players.get_single() {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("ldtk project should be loaded before player is spawned");
-
-        for (level_iid, level_transform) in levels.iter() {
-            let level = ldtk_project
-                .get_raw_level_by_iid(level_iid.get())
-                .expect("level should exist in only project");
-
-            let level_bounds = Rect {
-                min: Vec2::new(
-                    level_transform.translation().x,
-                    level_transform.translation().y,
-                ),
-                max: Vec2::new(
-                    level_transform.translation().x + level.px_wid as f32,
-                    level_transform.translation().y + level.px_hei as f32,
-                ),
-            };
-
-            if level_bounds.contains(player_transform.translation().truncate()) {
-                *level_selection = LevelSelection::Iid(level_iid.clone());
-            }
-        }
+fn player_stats(_: &EntityInstance) -> Stats {
+    Stats::new([
+        (Stat::MoveAcceleration, PLAYER_ACCELERATION),
+        (Stat::Damage, PLAYER_DAMAGE),
+        (Stat::FireRate, PLAYER_FIRE_RATE),
+        (Stat::ProjectileSpeed, PLAYER_PROJECTILE_SPEED),
+        (Stat::ProjectileCount, 1.),
+        (Stat::Pierce, 0.),
+        (Stat::MaxHealth, PLAYER_HEALTH as f32),
+    ])
+}
+
+fn player_mana(_: &EntityInstance) -> Mana {
+    Mana::new(PLAYER_MANA)
+}
+
+fn player_magnet(_: &EntityInstance) -> Magnet {
+    Magnet {
+        radius: PLAYER_MAGNET_RADIUS,
     }
 }
+
+fn player_collision_layers(_: &EntityInstance) -> CollisionLayers {
+    Layer::player()
+}
+
+fn player_hurtbox(_: &EntityInstance) -> Hurtbox {
+    Hurtbox::new(Team::Player)
+}
@@ -0,0 +1,261 @@
+//! Experience earned by killing mobs and upgrades chosen when the player levels up
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    combat::{apply_damage, despawn_dead, Health},
+    player::Player,
+    ron_asset::RonAssetPlugin,
+    run::{pause_time, unpause_time, RunRng, RunState},
+    stats::{Modifier, ModifierSource, Stats},
+};
+
+use self::ui::*;
+
+mod ui;
+
+/// File listing the upgrades offered on level up, relative to the assets folder
+pub const UPGRADE_POOL_PATH: &str = "upgrades/pool.upgrades.ron";
+/// Experience needed to reach level 2, each level needs this much more than the previous one
+pub const LEVEL_EXPERIENCE_STEP: u32 = 20;
+/// Number of upgrades offered on each level up
+pub const UPGRADE_CHOICES: usize = 3;
+
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RonAssetPlugin::<UpgradePool>::new(&["upgrades.ron"]),
+            LevelUpUiPlugin,
+        ))
+        .init_resource::<Upgrades>()
+        .init_resource::<UpgradeChoices>()
+        .add_event::<UpgradeChosen>()
+        .add_systems(
+            Update,
+            (
+                award_experience.after(apply_damage).before(despawn_dead),
+                start_level_up.run_if(in_state(RunState::Playing)),
+                apply_chosen_upgrade.run_if(in_state(RunState::LevelUp)),
+            ),
+        )
+        .add_systems(OnEnter(RunState::LevelUp), (pause_time, offer_upgrades))
+        .add_systems(OnExit(RunState::LevelUp), (unpause_time, clear_upgrades));
+    }
+}
+
+/// Upgrades drawn from on level up, loaded from [`UPGRADE_POOL_PATH`]
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct UpgradePool {
+    pub upgrades: Vec<Upgrade>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Upgrade {
+    pub name: String,
+    pub description: String,
+    /// Added to the player's [`Stats`] for the rest of the run
+    pub modifiers: Vec<Modifier>,
+}
+
+impl UpgradePool {
+    /// Indices of up to `count` different upgrades, in random order
+    pub fn roll(&self, count: usize, rng: &mut fastrand::Rng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.upgrades.len()).collect();
+        rng.shuffle(&mut indices);
+        indices.truncate(count);
+        indices
+    }
+}
+
+/// Handle keeping the [`UpgradePool`] loaded
+#[derive(Resource)]
+pub struct Upgrades(pub Handle<UpgradePool>);
+
+impl FromWorld for Upgrades {
+    fn from_world(world: &mut World) -> Self {
+        Upgrades(world.resource::<AssetServer>().load(UPGRADE_POOL_PATH))
+    }
+}
+
+/// Indices in the [`UpgradePool`] of the upgrades currently offered
+#[derive(Resource, Default)]
+pub struct UpgradeChoices(pub Vec<usize>);
+
+/// An event sent when the player picks the upgrade at this index of the [`UpgradePool`]
+#[derive(Event)]
+pub struct UpgradeChosen(pub usize);
+
+/// Experience given to the player when the entity dies
+#[derive(Component, Default)]
+pub struct ExperienceReward(pub u32);
+
+#[derive(Component, Debug)]
+pub struct Experience {
+    pub level: u32,
+    /// Experience earned since the last level up
+    pub points: u32,
+    /// Level ups whose upgrade was not chosen yet
+    pub pending_level_ups: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience {
+            level: 1,
+            points: 0,
+            pending_level_ups: 0,
+        }
+    }
+}
+
+impl Experience {
+    /// Experience needed to go from `level` to the next one
+    pub fn to_next_level(level: u32) -> u32 {
+        LEVEL_EXPERIENCE_STEP * level
+    }
+
+    /// Adds experience, leveling up as many times as it allows
+    pub fn gain(&mut self, points: u32) {
+        self.points += points;
+        while self.points >= Experience::to_next_level(self.level) {
+            self.points -= Experience::to_next_level(self.level);
+            self.level += 1;
+            self.pending_level_ups += 1;
+        }
+    }
+}
+
+fn award_experience(
+    reward_query: Query<(&ExperienceReward, &Health), Changed<Health>>,
+    mut player_query: Query<&mut Experience, With<Player>>,
+) {
+    for (reward, health) in reward_query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+        for mut experience in player_query.iter_mut() {
+            experience.gain(reward.0);
+        }
+    }
+}
+
+fn start_level_up(
+    player_query: Query<&Experience, (With<Player>, Changed<Experience>)>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    if player_query
+        .iter()
+        .any(|experience| experience.pending_level_ups > 0)
+    {
+        next_run_state.set(RunState::LevelUp);
+    }
+}
+
+/// Rolls the upgrades offered for the pending level up
+fn offer_upgrades(
+    upgrades: Res<Upgrades>,
+    pools: Res<Assets<UpgradePool>>,
+    mut rng: ResMut<RunRng>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    match pools.get(&upgrades.0) {
+        Some(pool) if !pool.upgrades.is_empty() => {
+            choices.0 = pool.roll(UPGRADE_CHOICES, &mut rng.0);
+        }
+        // Nothing to choose from, the level ups are skipped
+        _ => next_run_state.set(RunState::Playing),
+    }
+}
+
+fn clear_upgrades(mut choices: ResMut<UpgradeChoices>) {
+    choices.0.clear();
+}
+
+/// Applies the chosen upgrade, then offers the next pending level up or resumes the game
+fn apply_chosen_upgrade(
+    mut upgrade_chosen_reader: EventReader<UpgradeChosen>,
+    upgrades: Res<Upgrades>,
+    pools: Res<Assets<UpgradePool>>,
+    mut rng: ResMut<RunRng>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut player_query: Query<(&mut Experience, &mut Stats), With<Player>>,
+    mut next_run_state: ResMut<NextState<RunState>>,
+) {
+    let Some(UpgradeChosen(index)) = upgrade_chosen_reader.read().last() else {
+        return;
+    };
+    let (Some(pool), Ok((mut experience, mut stats))) =
+        (pools.get(&upgrades.0), player_query.get_single_mut())
+    else {
+        return;
+    };
+    let Some(upgrade) = pool.upgrades.get(*index) else {
+        return;
+    };
+
+    let mut modifiers = stats.modifiers(ModifierSource::Upgrade);
+    modifiers.extend(upgrade.modifiers.iter().copied());
+    stats.set_modifiers(ModifierSource::Upgrade, modifiers);
+
+    experience.pending_level_ups = experience.pending_level_ups.saturating_sub(1);
+    if experience.pending_level_ups > 0 {
+        choices.0 = pool.roll(UPGRADE_CHOICES, &mut rng.0);
+    } else {
+        next_run_state.set(RunState::Playing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_upgrade_pool_is_valid() {
+        let content = std::fs::read_to_string(format!("assets/{UPGRADE_POOL_PATH}")).unwrap();
+        let pool: UpgradePool = ron::from_str(&content).unwrap();
+
+        assert!(pool.upgrades.len() >= UPGRADE_CHOICES);
+        for upgrade in pool.upgrades {
+            assert!(
+                !upgrade.modifiers.is_empty(),
+                "{} does nothing",
+                upgrade.name
+            );
+        }
+    }
+
+    #[test]
+    fn experience_carries_over_several_level_ups() {
+        let mut experience = Experience::default();
+        experience.gain(LEVEL_EXPERIENCE_STEP * 3 + 1);
+
+        assert_eq!(experience.level, 3);
+        assert_eq!(experience.points, 1);
+        assert_eq!(experience.pending_level_ups, 2);
+    }
+
+    #[test]
+    fn rolled_upgrades_are_different() {
+        let pool = UpgradePool {
+            upgrades: (0..5)
+                .map(|i| Upgrade {
+                    name: i.to_string(),
+                    description: String::new(),
+                    modifiers: Vec::new(),
+                })
+                .collect(),
+        };
+
+        let mut choices = pool.roll(3, &mut fastrand::Rng::with_seed(0));
+        assert_eq!(choices.len(), 3);
+        choices.sort();
+        choices.dedup();
+        assert_eq!(choices.len(), 3);
+
+        assert_eq!(pool.roll(10, &mut fastrand::Rng::with_seed(0)).len(), 5);
+    }
+}
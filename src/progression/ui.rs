@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+use super::*;
+
+const TITLE_FONT_SIZE: f32 = 40.;
+const NAME_FONT_SIZE: f32 = 28.;
+const DESCRIPTION_FONT_SIZE: f32 = 20.;
+const BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+pub(super) struct LevelUpUiPlugin;
+
+impl Plugin for LevelUpUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                update_level_up_screen.run_if(resource_changed::<UpgradeChoices>()),
+                upgrade_button_interaction,
+            ),
+        );
+    }
+}
+
+/// Shown while the player chooses an upgrade
+#[derive(Component)]
+pub struct LevelUpScreen;
+
+/// Picks the upgrade at this index of the [`UpgradePool`] when clicked
+#[derive(Component)]
+struct UpgradeButton(usize);
+
+fn update_level_up_screen(
+    mut commands: Commands,
+    choices: Res<UpgradeChoices>,
+    upgrades: Res<Upgrades>,
+    pools: Res<Assets<UpgradePool>>,
+    screen_query: Query<Entity, With<LevelUpScreen>>,
+    player_query: Query<&Experience, With<Player>>,
+) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    let Some(pool) = pools.get(&upgrades.0) else {
+        return;
+    };
+    if choices.0.is_empty() {
+        return;
+    }
+    let level = player_query
+        .get_single()
+        .map_or(1, |experience| experience.level);
+
+    commands
+        .spawn((
+            LevelUpScreen,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                format!("Level {level}"),
+                TextStyle {
+                    font_size: TITLE_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+
+            for &index in &choices.0 {
+                let Some(upgrade) = pool.upgrades.get(index) else {
+                    continue;
+                };
+
+                screen
+                    .spawn((
+                        UpgradeButton(index),
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(360.),
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                padding: UiRect::all(Val::Px(8.)),
+                                row_gap: Val::Px(4.),
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            upgrade.name.clone(),
+                            TextStyle {
+                                font_size: NAME_FONT_SIZE,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                        button.spawn(TextBundle::from_section(
+                            upgrade.description.clone(),
+                            TextStyle {
+                                font_size: DESCRIPTION_FONT_SIZE,
+                                color: Color::GRAY,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn upgrade_button_interaction(
+    mut interaction_query: Query<
+        (&Interaction, &UpgradeButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut upgrade_chosen_writer: EventWriter<UpgradeChosen>,
+) {
+    for (interaction, UpgradeButton(index), mut background_color) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Pressed => upgrade_chosen_writer.send(UpgradeChosen(*index)),
+            Interaction::Hovered => *background_color = HOVERED_BUTTON_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Progress of the current run, from the first room to the defeat of the final boss or the player
+
+use bevy::prelude::*;
+
+const RUN_OVER_FONT_SIZE: f32 = 64.;
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<RunState>()
+            .init_resource::<RunRng>()
+            .add_systems(OnEnter(RunState::Victory), spawn_victory_screen)
+            .add_systems(OnEnter(RunState::Defeat), (pause_time, spawn_defeat_screen));
+    }
+}
+
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RunState {
+    #[default]
+    Playing,
+    /// Gameplay is paused while the player chooses an upgrade
+    LevelUp,
+    /// Gameplay is paused while the camera pans to the room the player entered
+    RoomTransition,
+    /// The final boss was defeated
+    Victory,
+    /// The player died, gameplay is paused for good
+    Defeat,
+}
+
+/// Source of the random rolls of the run, such as loot
+#[derive(Resource, Default)]
+pub struct RunRng(pub fastrand::Rng);
+
+/// Freezes the gameplay, used on entering the states pausing it
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+#[derive(Component)]
+pub struct VictoryScreen;
+
+#[derive(Component)]
+pub struct DefeatScreen;
+
+fn spawn_victory_screen(mut commands: Commands) {
+    spawn_run_over_screen(&mut commands, VictoryScreen, "Victory!");
+}
+
+fn spawn_defeat_screen(mut commands: Commands) {
+    spawn_run_over_screen(&mut commands, DefeatScreen, "Defeat");
+}
+
+fn spawn_run_over_screen(commands: &mut Commands, marker: impl Component, text: &str) {
+    commands
+        .spawn((
+            marker,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: RUN_OVER_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
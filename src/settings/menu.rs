@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use super::*;
+
+const RESOLUTIONS: [(u32, u32); 5] = [
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+const FRAME_LIMITS: [FrameLimit; 6] = [
+    FrameLimit::Auto,
+    FrameLimit::Fps(30),
+    FrameLimit::Fps(60),
+    FrameLimit::Fps(120),
+    FrameLimit::Fps(144),
+    FrameLimit::Off,
+];
+const CAMERA_ZOOMS: [f32; 5] = [0.2, 0.25, 0.35, 0.5, 0.75];
+const VOLUME_STEP: f32 = 0.1;
+
+const MENU_FONT_SIZE: f32 = 28.;
+const BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+pub(super) struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_settings_menu).add_systems(
+            Update,
+            (
+                toggle_settings_menu,
+                settings_button_interaction,
+                update_settings_text.run_if(resource_changed::<Settings>()),
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct SettingsMenu;
+
+/// A single line of the settings menu
+#[derive(Component, Clone, Copy)]
+enum SettingKind {
+    Resolution,
+    WindowMode,
+    Vsync,
+    FrameLimit,
+    CameraZoom,
+    RoomTransition,
+    MasterVolume,
+}
+
+impl SettingKind {
+    const ALL: [SettingKind; 7] = [
+        SettingKind::Resolution,
+        SettingKind::WindowMode,
+        SettingKind::Vsync,
+        SettingKind::FrameLimit,
+        SettingKind::CameraZoom,
+        SettingKind::RoomTransition,
+        SettingKind::MasterVolume,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingKind::Resolution => "Resolution",
+            SettingKind::WindowMode => "Window mode",
+            SettingKind::Vsync => "VSync",
+            SettingKind::FrameLimit => "Frame limit",
+            SettingKind::CameraZoom => "Camera zoom",
+            SettingKind::RoomTransition => "Room transition",
+            SettingKind::MasterVolume => "Master volume",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        match self {
+            SettingKind::Resolution => {
+                format!("{}x{}", settings.resolution.0, settings.resolution.1)
+            }
+            SettingKind::WindowMode => format!("{:?}", settings.window_mode),
+            SettingKind::Vsync => if settings.vsync { "On" } else { "Off" }.into(),
+            SettingKind::FrameLimit => match settings.frame_limit {
+                FrameLimit::Auto => "Auto".into(),
+                FrameLimit::Fps(fps) => format!("{fps} fps"),
+                FrameLimit::Off => "Off".into(),
+            },
+            SettingKind::CameraZoom => format!("{:.2}", settings.camera_zoom),
+            SettingKind::RoomTransition => format!("{:?}", settings.room_transition),
+            SettingKind::MasterVolume => format!("{:.0}%", settings.audio.master * 100.),
+        }
+    }
+
+    /// Switch the setting to its next value, wrapping around
+    fn cycle(&self, settings: &mut Settings) {
+        match self {
+            SettingKind::Resolution => {
+                settings.resolution = next(&RESOLUTIONS, &settings.resolution)
+            }
+            SettingKind::WindowMode => {
+                settings.window_mode = match settings.window_mode {
+                    WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+                    WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+                    WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+                }
+            }
+            SettingKind::Vsync => settings.vsync = !settings.vsync,
+            SettingKind::FrameLimit => {
+                settings.frame_limit = next(&FRAME_LIMITS, &settings.frame_limit)
+            }
+            SettingKind::CameraZoom => {
+                settings.camera_zoom = next(&CAMERA_ZOOMS, &settings.camera_zoom)
+            }
+            SettingKind::RoomTransition => {
+                settings.room_transition = match settings.room_transition {
+                    RoomTransitionMode::Follow => RoomTransitionMode::Pan,
+                    RoomTransitionMode::Pan => RoomTransitionMode::Follow,
+                }
+            }
+            SettingKind::MasterVolume => cycle_volume(&mut settings.audio.master),
+        }
+    }
+}
+
+/// Value following `current` in `values`, or the first one if `current` is not in the list
+fn next<T: Copy + PartialEq>(values: &[T], current: &T) -> T {
+    let index = values
+        .iter()
+        .position(|value| value == current)
+        .map_or(0, |index| (index + 1) % values.len());
+    values[index]
+}
+
+fn cycle_volume(volume: &mut f32) {
+    *volume = if *volume >= 1. - VOLUME_STEP / 2. {
+        0.
+    } else {
+        ((*volume + VOLUME_STEP) * 10.).round() / 10.
+    };
+}
+
+#[derive(Component)]
+struct SettingValueText(SettingKind);
+
+fn spawn_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    let text_style = TextStyle {
+        font_size: MENU_FONT_SIZE,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            SettingsMenu,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section("Settings", text_style.clone()));
+
+            for kind in SettingKind::ALL {
+                menu.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(520.),
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(TextBundle::from_section(kind.label(), text_style.clone()));
+                    row.spawn((
+                        kind,
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                justify_content: JustifyContent::Center,
+                                padding: UiRect::all(Val::Px(4.)),
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            SettingValueText(kind),
+                            TextBundle::from_section(kind.value(&settings), text_style.clone()),
+                        ));
+                    });
+                });
+            }
+        });
+}
+
+fn toggle_settings_menu(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut menu_query: Query<&mut Visibility, With<SettingsMenu>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for mut visibility in menu_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Visible,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Cycle the clicked setting and write the result back to [`SETTINGS_PATH`]
+fn settings_button_interaction(
+    mut interaction_query: Query<
+        (&Interaction, &SettingKind, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, kind, mut background_color) in interaction_query.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {
+                kind.cycle(&mut settings);
+                settings.save(SETTINGS_PATH);
+            }
+            Interaction::Hovered => *background_color = HOVERED_BUTTON_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+fn update_settings_text(
+    settings: Res<Settings>,
+    mut text_query: Query<(&mut Text, &SettingValueText)>,
+) {
+    for (mut text, SettingValueText(kind)) in text_query.iter_mut() {
+        text.sections[0].value = kind.value(&settings);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode, WindowResolution},
+};
+use bevy_framepace::{FramepaceSettings, Limiter};
+use serde::{Deserialize, Serialize};
+
+use self::menu::*;
+
+mod menu;
+
+/// File the settings are read from at startup and written back to by the settings menu
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .add_plugins(SettingsMenuPlugin)
+            .add_systems(
+                Update,
+                (
+                    apply_window_settings,
+                    apply_framepace_settings,
+                    apply_audio_settings,
+                )
+                    .run_if(resource_changed::<Settings>()),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub resolution: (u32, u32),
+    pub window_mode: WindowModeSetting,
+    pub vsync: bool,
+    pub frame_limit: FrameLimit,
+    /// Scale of the player camera projection, smaller values zoom in
+    pub camera_zoom: f32,
+    pub room_transition: RoomTransitionMode,
+    pub audio: AudioSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: (1600, 900),
+            window_mode: WindowModeSetting::Windowed,
+            vsync: true,
+            frame_limit: FrameLimit::Auto,
+            camera_zoom: 0.35,
+            room_transition: RoomTransitionMode::Follow,
+            audio: AudioSettings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameLimit {
+    /// Limit to the refresh rate of the monitor
+    #[default]
+    Auto,
+    Fps(u32),
+    Off,
+}
+
+impl From<FrameLimit> for Limiter {
+    fn from(frame_limit: FrameLimit) -> Self {
+        match frame_limit {
+            FrameLimit::Auto => Limiter::Auto,
+            FrameLimit::Fps(fps) => Limiter::from_framerate(fps as f64),
+            FrameLimit::Off => Limiter::Off,
+        }
+    }
+}
+
+/// How the camera moves when the player goes from a room to the next
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomTransitionMode {
+    /// The camera stays centered on the player
+    #[default]
+    Follow,
+    /// The camera stays within the current room and pans to the next one
+    /// while the game is frozen
+    Pan,
+}
+
+/// Volumes are between 0 and 1, music and effects get their own once the game plays any
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { master: 1. }
+    }
+}
+
+impl Settings {
+    /// Read settings from `path`, falling back to the defaults if the file is missing or invalid
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|error| {
+                warn!("invalid settings file {}: {error}", path.display());
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| fs::write(path, content).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            error!("could not save settings to {}: {error}", path.display());
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn window_resolution(&self) -> WindowResolution {
+        (self.resolution.0 as f32, self.resolution.1 as f32).into()
+    }
+
+    /// Primary window as described by the settings, used to build the `WindowPlugin`
+    pub fn primary_window(&self) -> Window {
+        Window {
+            title: "Ra Lex Jin".into(),
+            resolution: self.window_resolution(),
+            present_mode: self.present_mode(),
+            mode: self.window_mode.into(),
+            // Tells wasm to resize the window according to the available canvas
+            fit_canvas_to_parent: true,
+            // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
+            prevent_default_event_handling: false,
+            ..default()
+        }
+    }
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Ok(mut window) = window_query.get_single_mut() {
+        let (width, height) = settings.resolution;
+        window.resolution.set(width as f32, height as f32);
+        window.mode = settings.window_mode.into();
+        window.present_mode = settings.present_mode();
+    }
+}
+
+fn apply_framepace_settings(
+    settings: Res<Settings>,
+    mut framepace_settings: ResMut<FramepaceSettings>,
+) {
+    framepace_settings.limiter = settings.frame_limit.into();
+}
+
+fn apply_audio_settings(settings: Res<Settings>, mut global_volume: ResMut<GlobalVolume>) {
+    *global_volume = GlobalVolume::new(settings.audio.master);
+}
@@ -0,0 +1,552 @@
+//! Headless game used by automated gameplay tests
+//!
+//! The game logic runs on top of [`MinimalPlugins`], without window, renderer or audio,
+//! and every frame advances time by exactly `1 / SIMULATION_FPS` seconds
+//! so that runs are deterministic.
+
+use std::{
+    fs,
+    path::{self, Path},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{PluginsState, SubApp},
+    asset::{
+        io::{
+            memory::{Dir, MemoryAssetReader},
+            AssetSource,
+        },
+        AssetPlugin,
+    },
+    input::InputPlugin,
+    prelude::*,
+    render::{render_resource::Shader, RenderApp},
+    time::TimeUpdateStrategy,
+};
+use bevy_ecs_ldtk::{
+    ldtk::{LayerInstance, LdtkJson, Level, NeighbourLevel},
+    prelude::*,
+};
+use bevy_xpbd_2d::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    boss::{Boss, BossMarker, BossPlugin},
+    character_controller_dynamic::MovementAction,
+    combat::CombatPlugin,
+    dungeon::DungeonPlugin,
+    interactable::{InteractAction, InteractablePlugin},
+    inventory::InventoryPlugin,
+    loot::LootPlugin,
+    mob::{Mob, MobBehavior, MobPlugin},
+    player::{attack::AttackAction, Player, PlayerPlugin},
+    progression::ProgressionPlugin,
+    run::RunPlugin,
+    settings::Settings,
+    stats::StatsPlugin,
+};
+
+pub const SIMULATION_FPS: u32 = 60;
+
+/// Maximum real time spent waiting for assets to load
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Asset source holding the maps edited by [`Simulation::with_map`]
+const EDITED_MAP_SOURCE: &str = "edited";
+
+const ENTITY_LAYER: &str = "Entities";
+
+pub struct Simulation {
+    pub app: App,
+}
+
+impl Simulation {
+    /// Build the game on the LDtk project `map`, relative to the assets folder,
+    /// and step it until the player has spawned and the mobs and bosses are read from LDtk.
+    pub fn new(map: &str) -> Self {
+        Self::build(map.into(), None)
+    }
+
+    /// Build the game like [`Simulation::new`] on a copy of `map` changed by `edit`,
+    /// so that tests make the rooms they need from one fixture.
+    pub fn with_map(map: &str, edit: impl FnOnce(&mut MapEditor)) -> Self {
+        let mut editor = MapEditor::load(map);
+        edit(&mut editor);
+        let (path, dir) = editor.into_asset_dir();
+        Self::build(format!("{EDITED_MAP_SOURCE}://{path}"), Some(dir))
+    }
+
+    fn build(map: String, edited_map: Option<Dir>) -> Self {
+        let mut app = App::new();
+
+        // Asset sources are registered before the asset plugin builds them
+        if let Some(dir) = edited_map {
+            app.register_asset_source(
+                EDITED_MAP_SOURCE,
+                AssetSource::build()
+                    .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+            );
+        }
+
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+            ImagePlugin::default_nearest(),
+        ))
+        .init_resource::<ClearColor>()
+        .init_asset::<TextureAtlas>()
+        .init_asset::<Shader>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1. / SIMULATION_FPS as f64,
+        )));
+
+        // The tilemap plugin added by `LdtkPlugin` expects a render sub app while it is built,
+        // it gracefully skips its render resources if that sub app is gone once plugins finish.
+        app.insert_sub_app(RenderApp, SubApp::new(App::empty(), |_, _| {}));
+        app.add_plugins(LdtkPlugin);
+        app.remove_sub_app(RenderApp);
+
+        app.add_plugins((
+            PhysicsPlugins::default(),
+            PlayerPlugin,
+            DungeonPlugin { map },
+            MobPlugin,
+            BossPlugin,
+            InteractablePlugin,
+            CombatPlugin,
+            LootPlugin,
+            InventoryPlugin,
+            RunPlugin,
+            StatsPlugin,
+            ProgressionPlugin,
+        ))
+        .init_resource::<Settings>()
+        .insert_resource(Gravity::ZERO);
+
+        while app.plugins_state() != PluginsState::Ready {
+            thread::yield_now();
+        }
+        app.finish();
+        app.cleanup();
+
+        let mut simulation = Simulation { app };
+        simulation.wait_for_player();
+        simulation.wait_for_mobs();
+        simulation
+    }
+
+    fn wait_for_player(&mut self) {
+        self.step_until("player spawned", |simulation| {
+            !simulation.entities_with::<Player>().is_empty()
+        });
+    }
+
+    fn wait_for_mobs(&mut self) {
+        self.step_until("mobs assembled", |simulation| {
+            let world = simulation.world();
+            world
+                .query_filtered::<(), (With<Mob>, Without<MobBehavior>)>()
+                .iter(world)
+                .next()
+                .is_none()
+                && world
+                    .query_filtered::<(), (With<BossMarker>, Without<Boss>)>()
+                    .iter(world)
+                    .next()
+                    .is_none()
+        });
+    }
+
+    /// Step until `condition` holds, giving assets loaded in the background some real time
+    pub fn step_until(&mut self, description: &str, mut condition: impl FnMut(&mut Self) -> bool) {
+        let start = Instant::now();
+
+        while !condition(self) {
+            assert!(
+                start.elapsed() < LOAD_TIMEOUT,
+                "not {description} after {LOAD_TIMEOUT:?}"
+            );
+            self.step();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    pub fn step_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    pub fn step_seconds(&mut self, seconds: f32) {
+        self.step_frames((seconds * SIMULATION_FPS as f32).round() as u32);
+    }
+
+    /// Queue a movement input, handled during the next step
+    pub fn send_movement(&mut self, action: MovementAction) {
+        self.world().send_event(action);
+    }
+
+    /// Queue an attack input, handled during the next step
+    pub fn send_attack(&mut self, action: AttackAction) {
+        self.world().send_event(action);
+    }
+
+    /// Queue an interact input, handled during the next step
+    pub fn send_interact(&mut self) {
+        self.world().send_event(InteractAction);
+    }
+
+    /// Move toward `direction` for `frames` frames, in input space where y points down
+    pub fn hold_movement(&mut self, direction: Vec2, frames: u32) {
+        for _ in 0..frames {
+            self.send_movement(MovementAction::Move(direction));
+            self.step();
+        }
+    }
+
+    pub fn entities_with<C: Component>(&mut self) -> Vec<Entity> {
+        self.world()
+            .query_filtered::<Entity, With<C>>()
+            .iter(&self.app.world)
+            .collect()
+    }
+
+    pub fn player(&mut self) -> Entity {
+        self.entities_with::<Player>()[0]
+    }
+
+    /// World position of an entity
+    pub fn position(&self, entity: Entity) -> Vec2 {
+        self.app
+            .world
+            .get::<GlobalTransform>(entity)
+            .expect("entity should have a transform")
+            .translation()
+            .truncate()
+    }
+
+    /// Move the player to a world position, the world it belongs to is at the origin
+    pub fn teleport_player(&mut self, position: Vec2) {
+        let player = self.player();
+        let mut transform = self
+            .world()
+            .get_mut::<Transform>(player)
+            .expect("player should have a transform");
+        transform.translation = position.extend(transform.translation.z);
+    }
+
+    /// Respawn the levels as if the LDtk project had been modified on disk
+    pub fn reload_map(&mut self) {
+        let handle = self
+            .world()
+            .query::<&Handle<LdtkProject>>()
+            .single(&self.app.world)
+            .clone();
+        self.world()
+            .send_event(AssetEvent::Modified { id: handle.id() });
+    }
+
+    /// LDtk project the levels are spawned from
+    fn ldtk_project(&mut self) -> LdtkProject {
+        let handle = self
+            .world()
+            .query::<&Handle<LdtkProject>>()
+            .single(&self.app.world)
+            .clone();
+        self.app
+            .world
+            .resource::<Assets<LdtkProject>>()
+            .get(handle)
+            .expect("project should be loaded")
+            .clone()
+    }
+
+    /// IID of the level named `identifier`
+    pub fn level_iid(&mut self, identifier: &str) -> LevelIid {
+        self.ldtk_project()
+            .iter_raw_levels()
+            .find(|level| level.identifier == identifier)
+            .map(|level| LevelIid::new(level.iid.clone()))
+            .unwrap_or_else(|| panic!("level {identifier} should exist"))
+    }
+
+    /// World position of the bottom left corner of the level named `identifier`
+    pub fn level_origin(&mut self, identifier: &str) -> Vec2 {
+        let ldtk_project = self.ldtk_project();
+
+        let level_entity = self
+            .world()
+            .query::<(Entity, &LevelIid)>()
+            .iter(&self.app.world)
+            .find(|(_, level_iid)| {
+                ldtk_project
+                    .get_raw_level_by_iid(level_iid.get())
+                    .is_some_and(|level| level.identifier == identifier)
+            })
+            .map(|(entity, _)| entity)
+            .unwrap_or_else(|| panic!("level {identifier} should be spawned"));
+
+        self.position(level_entity)
+    }
+}
+
+/// Copy of an LDtk project that tests change before [`Simulation::with_map`] loads it
+pub struct MapEditor {
+    /// Path of the original project, relative to the assets folder
+    path: String,
+    pub project: LdtkJson,
+    /// Entity definitions as written by LDtk, to build new instances from
+    entity_definitions: Vec<Value>,
+    /// Number of IIDs given to new entities and levels
+    new_iids: usize,
+}
+
+impl MapEditor {
+    fn load(path: &str) -> Self {
+        let content = fs::read_to_string(format!("assets/{path}"))
+            .unwrap_or_else(|error| panic!("cannot read {path}: {error}"));
+        let raw: Value = serde_json::from_str(&content).expect("map should be JSON");
+        let entity_definitions = raw["defs"]["entities"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        MapEditor {
+            path: path.into(),
+            project: serde_json::from_value(raw).expect("map should be an LDtk project"),
+            entity_definitions,
+            new_iids: 0,
+        }
+    }
+
+    /// Folder holding the edited project at its root and the tilesets it uses,
+    /// with the path of the project in it
+    fn into_asset_dir(mut self) -> (String, Dir) {
+        let dir = Dir::default();
+        let map_folder = Path::new(&self.path).parent().unwrap_or(Path::new(""));
+
+        // Tilesets are relative to the project, which moves to the root of the folder
+        for tileset in &mut self.project.defs.tilesets {
+            let Some(rel_path) = tileset.rel_path.as_mut() else {
+                continue;
+            };
+            let path = normalize(&map_folder.join(&*rel_path));
+            let image = fs::read(format!("assets/{path}"))
+                .unwrap_or_else(|error| panic!("cannot read tileset {path}: {error}"));
+            dir.insert_asset(Path::new(&path), image);
+            *rel_path = path;
+        }
+
+        let file_name = Path::new(&self.path)
+            .file_name()
+            .expect("map path should name a file")
+            .to_string_lossy()
+            .into_owned();
+        let content = serde_json::to_string(&self.project).expect("map should serialize");
+        dir.insert_asset_text(Path::new(&file_name), &content);
+        (file_name, dir)
+    }
+
+    pub fn level_mut(&mut self, level: &str) -> &mut Level {
+        self.project
+            .levels
+            .iter_mut()
+            .find(|candidate| candidate.identifier == level)
+            .unwrap_or_else(|| panic!("level {level} should exist"))
+    }
+
+    pub fn layer_mut(&mut self, level: &str, layer: &str) -> &mut LayerInstance {
+        self.level_mut(level)
+            .layer_instances
+            .iter_mut()
+            .flatten()
+            .find(|candidate| candidate.identifier == layer)
+            .unwrap_or_else(|| panic!("layer {layer} should exist in {level}"))
+    }
+
+    /// Set the cells of an IntGrid layer to `value`, in LDtk grid coordinates where y points down
+    pub fn set_cells(
+        &mut self,
+        level: &str,
+        layer: &str,
+        cells: impl IntoIterator<Item = IVec2>,
+        value: i32,
+    ) {
+        let layer = self.layer_mut(level, layer);
+        for cell in cells {
+            assert!(
+                (0..layer.c_wid).contains(&cell.x) && (0..layer.c_hei).contains(&cell.y),
+                "cell {cell} outside of {}",
+                layer.identifier
+            );
+            layer.int_grid_csv[(cell.y * layer.c_wid + cell.x) as usize] = value;
+        }
+    }
+
+    /// Remove the entities named `identifier` from a level
+    pub fn remove_entities(&mut self, level: &str, identifier: &str) {
+        self.layer_mut(level, ENTITY_LAYER)
+            .entity_instances
+            .retain(|entity| entity.identifier != identifier);
+    }
+
+    /// Place an entity named `identifier` with its pivot at `px` in a level, fields missing
+    /// from `fields` are left empty. Returns the IID of the new entity.
+    pub fn place_entity(
+        &mut self,
+        level: &str,
+        identifier: &str,
+        px: IVec2,
+        fields: &[(&str, Value)],
+    ) -> String {
+        let definition = self
+            .entity_definitions
+            .iter()
+            .find(|definition| definition["identifier"] == identifier)
+            .unwrap_or_else(|| panic!("entity {identifier} should be defined"))
+            .clone();
+        let iid = self.new_iid(identifier);
+
+        let field_instances: Vec<Value> = definition["fieldDefs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|field| {
+                let name = field["identifier"].as_str().unwrap_or_default();
+                let field_type = field["__type"].as_str().unwrap_or_default();
+                let value = match fields.iter().find(|(candidate, _)| *candidate == name) {
+                    Some((_, value)) => value.clone(),
+                    None if field_type.starts_with("Array<") => json!([]),
+                    None => Value::Null,
+                };
+                json!({
+                    "__identifier": name,
+                    "__type": field_type,
+                    "__value": value,
+                    "__tile": null,
+                    "defUid": field["uid"],
+                    "realEditorValues": [],
+                })
+            })
+            .collect();
+
+        let level_instance = self.level_mut(level);
+        let world = IVec2::new(level_instance.world_x, level_instance.world_y) + px;
+        let layer = self.layer_mut(level, ENTITY_LAYER);
+        let grid = px.div_euclid(IVec2::splat(layer.grid_size));
+        let entity = json!({
+            "__identifier": identifier,
+            "__grid": [grid.x, grid.y],
+            "__pivot": [definition["pivotX"], definition["pivotY"]],
+            "__tags": definition["tags"],
+            "__tile": definition["tileRect"],
+            "__smartColor": definition["color"],
+            "iid": iid,
+            "width": definition["width"],
+            "height": definition["height"],
+            "defUid": definition["uid"],
+            "px": [px.x, px.y],
+            "fieldInstances": field_instances,
+            "__worldX": world.x,
+            "__worldY": world.y,
+        });
+        layer
+            .entity_instances
+            .push(serde_json::from_value(entity).expect("entity should be an LDtk entity"));
+        iid
+    }
+
+    /// Value of an EntityRef field pointing to the entity `iid` of a level
+    pub fn entity_ref(&mut self, level: &str, iid: &str) -> Value {
+        let world_iid = self.project.iid.clone();
+        let level_iid = self.level_mut(level).iid.clone();
+        let layer_iid = self.layer_mut(level, ENTITY_LAYER).iid.clone();
+        json!({
+            "entityIid": iid,
+            "layerIid": layer_iid,
+            "levelIid": level_iid,
+            "worldIid": world_iid,
+        })
+    }
+
+    /// Add a copy of a level without its entities named `identifier`, `offset` pixels away
+    /// from it in the world, where it becomes its neighbour
+    pub fn copy_level(&mut self, level: &str, identifier: &str, offset: IVec2) {
+        let (direction, opposite) = match (offset.x.signum(), offset.y.signum()) {
+            (1, 0) => ("e", "w"),
+            (-1, 0) => ("w", "e"),
+            (0, 1) => ("s", "n"),
+            (0, -1) => ("n", "s"),
+            _ => panic!("levels are copied next to each other, not {offset} away"),
+        };
+        let uid = self
+            .project
+            .levels
+            .iter()
+            .map(|level| level.uid)
+            .max()
+            .unwrap_or_default()
+            + 1;
+
+        let mut copy = self.level_mut(level).clone();
+        copy.identifier = identifier.into();
+        copy.iid = self.new_iid(identifier);
+        copy.uid = uid;
+        copy.world_x += offset.x;
+        copy.world_y += offset.y;
+        for layer in copy.layer_instances.iter_mut().flatten() {
+            layer.iid = self.new_iid(&layer.identifier);
+            layer.level_id = uid;
+            layer.entity_instances.clear();
+        }
+
+        let original = self.level_mut(level);
+        copy.neighbours = vec![neighbour(opposite, &original.iid, original.uid)];
+        original
+            .neighbours
+            .push(neighbour(direction, &copy.iid, uid));
+        self.project.levels.push(copy);
+    }
+
+    fn new_iid(&mut self, name: &str) -> String {
+        self.new_iids += 1;
+        format!("{}-{}", name.to_lowercase(), self.new_iids)
+    }
+}
+
+fn neighbour(direction: &str, level_iid: &str, level_uid: i32) -> NeighbourLevel {
+    serde_json::from_value(json!({
+        "dir": direction,
+        "levelIid": level_iid,
+        "levelUid": level_uid,
+    }))
+    .expect("neighbour should be an LDtk neighbour")
+}
+
+/// Resolve the `..` and `.` of a relative path, joined with slashes like asset paths
+fn normalize(relative: &Path) -> String {
+    let mut components = Vec::new();
+    for component in relative.components() {
+        match component {
+            path::Component::ParentDir => {
+                components.pop();
+            }
+            path::Component::CurDir => {}
+            component => components.push(component.as_os_str().to_string_lossy()),
+        }
+    }
+    components.join("/")
+}
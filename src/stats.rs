@@ -0,0 +1,214 @@
+//! Base values of gameplay numbers and the modifiers stacked on top of them
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+
+use crate::combat::{status_effect::StatusKind, Health};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_max_health);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Stat {
+    MoveAcceleration,
+    /// Damage of the attacks without weapon, the modifiers also apply to weapon damage
+    Damage,
+    /// Weapon attacks per second
+    FireRate,
+    /// Speed of the projectiles without weapon, the modifiers also apply to weapon projectiles
+    ProjectileSpeed,
+    /// Projectiles fired by each weapon attack, spread around the aim direction
+    ProjectileCount,
+    /// Targets a projectile goes through before it is destroyed
+    Pierce,
+    MaxHealth,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum ModifierKind {
+    /// Added to the base value
+    Additive(f32),
+    /// Fraction of the value added once the additive modifiers are applied, `-0.5` halves it
+    Multiplicative(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Modifier {
+    pub stat: Stat,
+    pub kind: ModifierKind,
+}
+
+impl Modifier {
+    pub fn additive(stat: Stat, amount: f32) -> Self {
+        Modifier {
+            stat,
+            kind: ModifierKind::Additive(amount),
+        }
+    }
+
+    pub fn multiplicative(stat: Stat, fraction: f32) -> Self {
+        Modifier {
+            stat,
+            kind: ModifierKind::Multiplicative(fraction),
+        }
+    }
+}
+
+/// What a modifier comes from, a source replaces all its modifiers at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierSource {
+    Equipment,
+    /// Temporary bonuses such as potions
+    Buff,
+    /// Chosen when leveling up, they last for the whole run
+    Upgrade,
+    Status(StatusKind),
+    /// Hazard tiles the entity stands on, such as mud
+    Terrain,
+}
+
+/// Base values of an entity and the modifiers applied to them
+#[derive(Component, Debug, Clone, Default)]
+pub struct Stats {
+    base: HashMap<Stat, f32>,
+    modifiers: Vec<(ModifierSource, Modifier)>,
+    /// Base values with the modifiers applied, recomputed when the modifiers change
+    values: HashMap<Stat, f32>,
+}
+
+impl Stats {
+    pub fn new(base: impl IntoIterator<Item = (Stat, f32)>) -> Self {
+        let base: HashMap<Stat, f32> = base.into_iter().collect();
+        Stats {
+            values: base.clone(),
+            base,
+            modifiers: Vec::new(),
+        }
+    }
+
+    pub fn base(&self, stat: Stat) -> f32 {
+        self.base.get(&stat).copied().unwrap_or_default()
+    }
+
+    /// Value of the stat with the modifiers applied
+    pub fn get(&self, stat: Stat) -> f32 {
+        self.values.get(&stat).copied().unwrap_or_default()
+    }
+
+    /// Applies the modifiers of `stat` to another base value, such as the damage of a weapon
+    pub fn apply(&self, stat: Stat, base: f32) -> f32 {
+        let (additive, multiplier) = self
+            .modifiers
+            .iter()
+            .map(|(_, modifier)| modifier)
+            .filter(|modifier| modifier.stat == stat)
+            .fold(
+                (0., 1.),
+                |(additive, multiplier), modifier| match modifier.kind {
+                    ModifierKind::Additive(amount) => (additive + amount, multiplier),
+                    ModifierKind::Multiplicative(fraction) => {
+                        (additive, multiplier * (1. + fraction).max(0.))
+                    }
+                },
+            );
+
+        (base + additive) * multiplier
+    }
+
+    pub fn modifiers(&self, source: ModifierSource) -> Vec<Modifier> {
+        self.modifiers
+            .iter()
+            .filter(|(modifier_source, _)| *modifier_source == source)
+            .map(|(_, modifier)| *modifier)
+            .collect()
+    }
+
+    /// Replaces the modifiers of `source`
+    pub fn set_modifiers(&mut self, source: ModifierSource, modifiers: Vec<Modifier>) {
+        self.modifiers
+            .retain(|(modifier_source, _)| *modifier_source != source);
+        self.modifiers
+            .extend(modifiers.into_iter().map(|modifier| (source, modifier)));
+
+        self.values = self
+            .base
+            .iter()
+            .map(|(stat, base)| (*stat, self.apply(*stat, *base)))
+            .collect();
+    }
+}
+
+/// Keeps the maximum health of entities in sync with their [`Stat::MaxHealth`]
+fn apply_max_health(mut query: Query<(&Stats, &mut Health), Changed<Stats>>) {
+    for (stats, mut health) in query.iter_mut() {
+        if !stats.base.contains_key(&Stat::MaxHealth) {
+            continue;
+        }
+        health.max = stats.get(Stat::MaxHealth).round() as i32;
+        health.current = health.current.min(health.max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> Stats {
+        Stats::new([(Stat::Damage, 10.), (Stat::MoveAcceleration, 100.)])
+    }
+
+    #[test]
+    fn additive_modifiers_apply_before_multiplicative_ones() {
+        let mut stats = stats();
+        stats.set_modifiers(
+            ModifierSource::Equipment,
+            vec![
+                Modifier::multiplicative(Stat::Damage, 0.5),
+                Modifier::additive(Stat::Damage, 2.),
+            ],
+        );
+
+        assert_eq!(stats.get(Stat::Damage), 18.);
+        assert_eq!(stats.get(Stat::MoveAcceleration), 100.);
+        assert_eq!(stats.apply(Stat::Damage, 20.), 33.);
+    }
+
+    #[test]
+    fn setting_modifiers_replaces_those_of_the_same_source() {
+        let mut stats = stats();
+        stats.set_modifiers(
+            ModifierSource::Equipment,
+            vec![Modifier::additive(Stat::Damage, 5.)],
+        );
+        stats.set_modifiers(
+            ModifierSource::Buff,
+            vec![Modifier::additive(Stat::Damage, 1.)],
+        );
+        stats.set_modifiers(
+            ModifierSource::Equipment,
+            vec![Modifier::additive(Stat::Damage, 3.)],
+        );
+
+        assert_eq!(stats.get(Stat::Damage), 14.);
+
+        stats.set_modifiers(ModifierSource::Buff, Vec::new());
+        assert_eq!(stats.get(Stat::Damage), 13.);
+        assert_eq!(stats.base(Stat::Damage), 10.);
+    }
+
+    #[test]
+    fn multipliers_do_not_go_below_zero() {
+        let mut stats = stats();
+        stats.set_modifiers(
+            ModifierSource::Status(StatusKind::Slow),
+            vec![Modifier::multiplicative(Stat::MoveAcceleration, -1.5)],
+        );
+
+        assert_eq!(stats.get(Stat::MoveAcceleration), 0.);
+    }
+}
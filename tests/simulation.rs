@@ -0,0 +1,861 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::LevelSelection;
+use bevy_xpbd_2d::prelude::*;
+use ra_lex_jin::{
+    boss::{Boss, BossEngaged},
+    combat::status_effect::{StatusEffects, StatusKind},
+    combat::{
+        bullet_pattern::{BulletPattern, Shooter},
+        hitbox::{Hitbox, Team},
+        DamageEvent, FriendlyFire, Health,
+    },
+    dungeon::{
+        collisions::{Door, Terrain},
+        hazards::{Hazard, MUD_SLOW, SPIKES_DAMAGE},
+        rooms::{CurrentRoom, RoomEntered, RoomExited},
+    },
+    interactable::{
+        props::{Chest, Lever, Pot, PressurePlate},
+        signal::{Gate, SpikeTrap},
+    },
+    layers::Layer,
+    loot::{Coins, LootDrop, LootTable, Pickup},
+    mob::{Mob, MobBehavior},
+    player::{
+        attack::AttackAction, camera::PlayerCamera, Player, PLAYER_ACCELERATION, PLAYER_HEALTH,
+        PLAYER_MAGNET_RADIUS,
+    },
+    run::RunState,
+    settings::{RoomTransitionMode, Settings},
+    simulation::{MapEditor, Simulation},
+    stats::{Stat, Stats},
+};
+use serde_json::json;
+
+const FIXTURE: &str = "tests/room_0.ldtk";
+const ROOM: &str = "Room_0";
+const COINS: &str = "tests/coins.loot.ron";
+const GRID_SIZE: f32 = 16.;
+const ROOM_SIZE: Vec2 = Vec2::new(35. * GRID_SIZE, 19. * GRID_SIZE);
+
+#[test]
+fn fireball_kills_mob_in_room_0() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let mobs = simulation.entities_with::<Mob>();
+    assert_eq!(mobs.len(), 1);
+
+    let mob_position = simulation.position(mobs[0]);
+    simulation.send_attack(AttackAction::Weapon(mob_position));
+    simulation.step_seconds(2.);
+
+    assert!(simulation.entities_with::<Mob>().is_empty());
+}
+
+#[test]
+fn mobs_are_assembled_from_their_kind_archetype() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let mob = simulation.entities_with::<Mob>()[0];
+
+    // The mob of Room_0 is a slime
+    assert_eq!(simulation.world().get::<Health>(mob).unwrap().max, 10);
+    assert_eq!(
+        simulation.world().get::<MobBehavior>(mob),
+        Some(&MobBehavior::Chase)
+    );
+}
+
+#[test]
+fn player_cannot_walk_through_walls() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let player = simulation.player();
+    let start = simulation.position(player);
+    // The east wall of Room_0 is the last column of its 35 cells wide grid
+    let wall_x = simulation.level_origin("Room_0").x + 34. * GRID_SIZE;
+
+    simulation.hold_movement(Vec2::X, 180);
+    let end = simulation.position(player);
+
+    assert!(end.x > start.x + GRID_SIZE, "player should have moved");
+    assert!(
+        end.x < wall_x,
+        "player at {end} went through the wall at x = {wall_x}"
+    );
+}
+
+#[test]
+fn mob_bullet_pattern_hits_the_player() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let mob = simulation.entities_with::<Mob>()[0];
+    let player = simulation.player();
+    let pattern: Handle<BulletPattern> = simulation
+        .world()
+        .resource::<AssetServer>()
+        .load("patterns/aimed.pattern.ron");
+    // Only the projectiles of the mob can hurt the player, it neither chases nor touches them
+    simulation
+        .world()
+        .entity_mut(mob)
+        .insert((Shooter::new(Team::Enemy, pattern), MobBehavior::Still))
+        .remove::<Hitbox>();
+
+    simulation.step_until("player hit", |simulation| {
+        simulation.world().get::<Health>(player).unwrap().current < PLAYER_HEALTH
+    });
+}
+
+/// Room_0 with a second slime between the player and the first one, which shoots
+/// at the player through it. Returns the slime in the line of fire.
+fn mob_in_the_line_of_fire(friendly_fire: bool) -> (Simulation, Entity) {
+    let mut simulation = Simulation::with_map(FIXTURE, |map| {
+        map.place_entity(
+            ROOM,
+            "Mob",
+            IVec2::new(408, 176),
+            &[("Kind", json!("Slime"))],
+        );
+    });
+    simulation
+        .world()
+        .insert_resource(FriendlyFire(friendly_fire));
+    let player = simulation.player();
+    let player_position = simulation.position(player);
+    let mut mobs = simulation.entities_with::<Mob>();
+    mobs.sort_by(|&a, &b| {
+        let a = simulation.position(a).distance(player_position);
+        let b = simulation.position(b).distance(player_position);
+        a.total_cmp(&b)
+    });
+    let (target, shooter) = (mobs[0], mobs[1]);
+
+    let pattern: Handle<BulletPattern> = simulation
+        .world()
+        .resource::<AssetServer>()
+        .load("patterns/aimed.pattern.ron");
+    // Only the projectiles can hurt, the mobs neither move nor touch anyone
+    for mob in [target, shooter] {
+        simulation
+            .world()
+            .entity_mut(mob)
+            .insert(MobBehavior::Still)
+            .remove::<Hitbox>();
+    }
+    simulation
+        .world()
+        .entity_mut(shooter)
+        .insert(Shooter::new(Team::Enemy, pattern));
+
+    (simulation, target)
+}
+
+#[test]
+fn friendly_fire_lets_mob_projectiles_hit_other_mobs() {
+    let (mut simulation, target) = mob_in_the_line_of_fire(true);
+
+    simulation.step_until("mob hit by the other mob", |simulation| {
+        let health = simulation.world().get::<Health>(target).unwrap();
+        health.current < health.max
+    });
+}
+
+#[test]
+fn mob_projectiles_go_through_other_mobs_without_friendly_fire() {
+    let (mut simulation, target) = mob_in_the_line_of_fire(false);
+    let player = simulation.player();
+
+    simulation.step_until("player hit", |simulation| {
+        simulation.world().get::<Health>(player).unwrap().current < PLAYER_HEALTH
+    });
+    let health = simulation.world().get::<Health>(target).unwrap();
+    assert_eq!(health.current, health.max);
+}
+
+#[test]
+fn dead_mobs_drop_loot_that_the_player_collects() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let mob = simulation.entities_with::<Mob>()[0];
+    let player = simulation.player();
+    let loot_table: Handle<LootTable> = simulation
+        .world()
+        .resource::<AssetServer>()
+        .load("tests/coins.loot.ron");
+    simulation
+        .world()
+        .entity_mut(mob)
+        .insert(LootDrop(loot_table.clone()));
+    simulation.step_until("loot table loaded", |simulation| {
+        simulation
+            .world()
+            .resource::<Assets<LootTable>>()
+            .contains(&loot_table)
+    });
+
+    simulation.world().send_event(DamageEvent {
+        source: player,
+        target: mob,
+        amount: 10_000,
+        effect: None,
+    });
+    simulation.step_frames(2);
+    let pickups = simulation.entities_with::<Pickup>();
+    assert_eq!(pickups.len(), 3);
+
+    // Within the player's magnet radius, but not touching the player
+    let near_player = simulation.position(player) + Vec2::new(PLAYER_MAGNET_RADIUS - 4., 0.);
+    for pickup in pickups {
+        simulation
+            .world()
+            .get_mut::<Transform>(pickup)
+            .unwrap()
+            .translation = near_player.extend(0.);
+    }
+
+    simulation.step_until("loot collected", |simulation| {
+        simulation.entities_with::<Pickup>().is_empty()
+    });
+    assert_eq!(simulation.world().get::<Coins>(player).unwrap().0, 6);
+}
+
+#[test]
+fn reloading_the_map_keeps_the_player_and_rebuilds_the_level() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let player = simulation.player();
+    let mob = simulation.entities_with::<Mob>()[0];
+    let terrain_colliders = simulation.entities_with::<Terrain>().len();
+
+    simulation.world().get_mut::<Coins>(player).unwrap().0 = 7;
+    simulation
+        .world()
+        .get_mut::<Transform>(player)
+        .unwrap()
+        .translation
+        .x += 2. * GRID_SIZE;
+    simulation.step();
+    let position = simulation.position(player);
+
+    simulation.reload_map();
+    simulation.step_until("level respawned", |simulation| {
+        simulation
+            .entities_with::<Mob>()
+            .first()
+            .is_some_and(|&new_mob| new_mob != mob)
+    });
+    simulation.step_frames(2);
+
+    assert_eq!(simulation.entities_with::<Player>(), vec![player]);
+    assert_eq!(simulation.world().get::<Coins>(player).unwrap().0, 7);
+    assert!(simulation.position(player).distance(position) < 1.);
+    assert_eq!(
+        simulation.entities_with::<Terrain>().len(),
+        terrain_colliders
+    );
+}
+
+fn door_layers(simulation: &mut Simulation) -> Vec<CollisionLayers> {
+    simulation
+        .world()
+        .query_filtered::<&CollisionLayers, With<Door>>()
+        .iter(&simulation.app.world)
+        .copied()
+        .collect()
+}
+
+/// Room_0 with a final boss instead of the mob
+fn boss_room(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+    map.place_entity(
+        ROOM,
+        "Boss",
+        IVec2::new(440, 184),
+        &[
+            ("Definition", json!("bosses/lich.boss.ron")),
+            ("Final", json!(true)),
+        ],
+    );
+}
+
+#[test]
+fn defeating_the_final_boss_opens_the_doors_and_wins_the_run() {
+    let mut simulation = Simulation::with_map(FIXTURE, boss_room);
+    let boss = simulation.entities_with::<Boss>()[0];
+    let player = simulation.player();
+
+    simulation.step_until("boss engaged", |simulation| {
+        simulation.world().get::<BossEngaged>(boss).is_some()
+    });
+    simulation.step();
+    let doors = door_layers(&mut simulation);
+    assert!(!doors.is_empty());
+    assert!(doors.iter().all(|&layers| layers == Door::closed()));
+
+    simulation.world().send_event(DamageEvent {
+        source: player,
+        target: boss,
+        amount: 10_000,
+        effect: None,
+    });
+    simulation.step_frames(2);
+
+    assert!(simulation.entities_with::<Boss>().is_empty());
+    assert!(door_layers(&mut simulation)
+        .iter()
+        .all(|&layers| layers == Door::open()));
+    assert_eq!(
+        *simulation.world().resource::<State<RunState>>().get(),
+        RunState::Victory
+    );
+}
+
+#[test]
+fn leaving_the_boss_room_releases_the_boss_and_opens_the_doors() {
+    let mut simulation = Simulation::with_map(FIXTURE, |map| {
+        boss_room(map);
+        map.copy_level(ROOM, "Room_1", IVec2::new(ROOM_SIZE.x as i32, 0));
+    });
+    let boss = simulation.entities_with::<Boss>()[0];
+    simulation.step_until("boss engaged", |simulation| {
+        simulation.world().get::<BossEngaged>(boss).is_some()
+    });
+
+    let room_1_center = simulation.level_origin("Room_0") + ROOM_SIZE * Vec2::new(1.5, 0.5);
+    simulation.teleport_player(room_1_center);
+    wait_for_room(&mut simulation, "Room_1");
+    simulation.step_frames(2);
+
+    assert!(simulation.world().get::<BossEngaged>(boss).is_none());
+    assert!(door_layers(&mut simulation)
+        .iter()
+        .all(|&layers| layers == Door::open()));
+}
+
+#[test]
+fn player_death_loses_the_run_and_keeps_the_camera() {
+    let mut simulation = Simulation::new(FIXTURE);
+    let player = simulation.player();
+    simulation.step();
+    let camera = simulation.entities_with::<PlayerCamera>()[0];
+
+    simulation.world().send_event(DamageEvent {
+        source: player,
+        target: player,
+        amount: PLAYER_HEALTH,
+        effect: None,
+    });
+    simulation.step_frames(2);
+
+    assert_eq!(simulation.entities_with::<Player>(), vec![player]);
+    assert_eq!(simulation.entities_with::<PlayerCamera>(), vec![camera]);
+    assert_eq!(
+        *simulation.world().resource::<State<RunState>>().get(),
+        RunState::Defeat
+    );
+    assert!(simulation.world().resource::<Time<Virtual>>().is_paused());
+}
+
+fn wait_for_loot_table(simulation: &mut Simulation, entity: Entity) {
+    simulation.step_until("loot table loaded", |simulation| {
+        let Some(loot_drop) = simulation.world().get::<LootDrop>(entity) else {
+            return false;
+        };
+        let loot_table = loot_drop.0.clone();
+        simulation
+            .world()
+            .resource::<Assets<LootTable>>()
+            .contains(&loot_table)
+    });
+}
+
+/// Room_0 with a prop of each kind instead of the mob, the lever powers the gate
+/// and the pressure plate the spike trap
+fn interactables(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+    map.place_entity(
+        ROOM,
+        "Chest",
+        IVec2::new(40, 80),
+        &[("LootTable", json!(COINS))],
+    );
+    map.place_entity(
+        ROOM,
+        "Pot",
+        IVec2::new(40, 272),
+        &[("LootTable", json!(COINS))],
+    );
+
+    let gate = map.place_entity(ROOM, "Gate", IVec2::new(232, 80), &[]);
+    let gate = map.entity_ref(ROOM, &gate);
+    map.place_entity(
+        ROOM,
+        "Lever",
+        IVec2::new(136, 80),
+        &[("Targets", json!([gate]))],
+    );
+
+    let trap = map.place_entity(ROOM, "SpikeTrap", IVec2::new(232, 272), &[]);
+    let trap = map.entity_ref(ROOM, &trap);
+    map.place_entity(
+        ROOM,
+        "PressurePlate",
+        IVec2::new(136, 272),
+        &[("Targets", json!([trap]))],
+    );
+}
+
+#[test]
+fn chests_drop_their_loot_once() {
+    let mut simulation = Simulation::with_map(FIXTURE, interactables);
+    let chest = simulation.entities_with::<Chest>()[0];
+    let player = simulation.player();
+    wait_for_loot_table(&mut simulation, chest);
+
+    let next_to_chest = simulation.position(chest) + Vec2::new(1.25 * GRID_SIZE, 0.);
+    simulation.teleport_player(next_to_chest);
+    simulation.step();
+    simulation.send_interact();
+    simulation.step_until("loot collected", |simulation| {
+        simulation.world().get::<Coins>(player).unwrap().0 == 6
+    });
+
+    simulation.send_interact();
+    simulation.step_seconds(1.);
+    assert!(simulation.entities_with::<Pickup>().is_empty());
+    assert_eq!(simulation.world().get::<Coins>(player).unwrap().0, 6);
+}
+
+#[test]
+fn levers_toggle_the_gates_they_target() {
+    let mut simulation = Simulation::with_map(FIXTURE, interactables);
+    let lever = simulation.entities_with::<Lever>()[0];
+    let gate = simulation.entities_with::<Gate>()[0];
+    let gate_layers =
+        |simulation: &mut Simulation| *simulation.world().get::<CollisionLayers>(gate).unwrap();
+    assert_eq!(gate_layers(&mut simulation), Door::closed());
+
+    let next_to_lever = simulation.position(lever) + Vec2::new(1.25 * GRID_SIZE, 0.);
+    simulation.teleport_player(next_to_lever);
+    simulation.step();
+    simulation.send_interact();
+    simulation.step_frames(2);
+    assert_eq!(gate_layers(&mut simulation), Door::open());
+
+    simulation.send_interact();
+    simulation.step_frames(2);
+    assert_eq!(gate_layers(&mut simulation), Door::closed());
+}
+
+#[test]
+fn pressure_plates_retract_spike_traps_while_pressed() {
+    let mut simulation = Simulation::with_map(FIXTURE, interactables);
+    let plate = simulation.entities_with::<PressurePlate>()[0];
+    let trap = simulation.entities_with::<SpikeTrap>()[0];
+    let player = simulation.player();
+    let start = simulation.position(player);
+    let trap_layers =
+        |simulation: &mut Simulation| *simulation.world().get::<CollisionLayers>(trap).unwrap();
+
+    let trap_position = simulation.position(trap);
+    simulation.teleport_player(trap_position);
+    simulation.step_until("player hurt by the spikes", |simulation| {
+        simulation.world().get::<Health>(player).unwrap().current < PLAYER_HEALTH
+    });
+
+    let plate_position = simulation.position(plate);
+    simulation.teleport_player(plate_position);
+    simulation.step_until("spikes retracted", |simulation| {
+        trap_layers(simulation) == CollisionLayers::none()
+    });
+
+    simulation.teleport_player(start);
+    simulation.step_until("spikes armed", |simulation| {
+        trap_layers(simulation) == Layer::trigger()
+    });
+}
+
+#[test]
+fn pots_break_when_hit_and_drop_their_loot() {
+    let mut simulation = Simulation::with_map(FIXTURE, interactables);
+    let pot = simulation.entities_with::<Pot>()[0];
+    let player = simulation.player();
+    wait_for_loot_table(&mut simulation, pot);
+
+    let pot_position = simulation.position(pot);
+    simulation.teleport_player(pot_position + Vec2::new(3. * GRID_SIZE, 0.));
+    simulation.step();
+    simulation.send_attack(AttackAction::Weapon(pot_position));
+    simulation.step_until("pot broken", |simulation| {
+        simulation.entities_with::<Pot>().is_empty()
+    });
+
+    simulation.teleport_player(pot_position);
+    simulation.step_until("loot collected", |simulation| {
+        simulation.world().get::<Coins>(player).unwrap().0 == 6
+    });
+}
+
+/// Cells of the 2x2 patch whose top left cell is `cell`, in LDtk grid coordinates
+fn patch(cell: IVec2) -> [IVec2; 4] {
+    [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE].map(|offset| cell + offset)
+}
+
+/// Room_0 without the mob and with a 2x2 patch of each hazard
+fn hazards(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+    map.set_cells(ROOM, "Hazards", patch(IVec2::new(2, 3)), 1);
+    map.set_cells(ROOM, "Hazards", patch(IVec2::new(8, 3)), 2);
+    map.set_cells(ROOM, "Hazards", patch(IVec2::new(2, 15)), 3);
+    map.set_cells(ROOM, "Hazards", patch(IVec2::new(8, 15)), 4);
+}
+
+/// World position of the center of the 2x2 hazard patch whose top left cell is `cell`
+fn hazard_position(simulation: &mut Simulation, cell: Vec2) -> Vec2 {
+    simulation.level_origin("Room_0") + Vec2::new(cell.x + 1., 19. - (cell.y + 1.)) * GRID_SIZE
+}
+
+#[test]
+fn spikes_hurt_the_player_periodically() {
+    let mut simulation = Simulation::with_map(FIXTURE, hazards);
+    let player = simulation.player();
+    let health =
+        |simulation: &mut Simulation| simulation.world().get::<Health>(player).unwrap().current;
+
+    let spikes = hazard_position(&mut simulation, Vec2::new(2., 3.));
+    simulation.teleport_player(spikes);
+    simulation.step_until("player hurt by the spikes", |simulation| {
+        health(simulation) < PLAYER_HEALTH
+    });
+    assert_eq!(health(&mut simulation), PLAYER_HEALTH - SPIKES_DAMAGE);
+
+    simulation.step_until("player hurt again by the spikes", |simulation| {
+        health(simulation) == PLAYER_HEALTH - 2 * SPIKES_DAMAGE
+    });
+}
+
+#[test]
+fn falling_into_a_pit_brings_the_player_back_to_safe_ground() {
+    let mut simulation = Simulation::with_map(FIXTURE, hazards);
+    let player = simulation.player();
+
+    let safe_spot = hazard_position(&mut simulation, Vec2::new(5., 3.));
+    simulation.teleport_player(safe_spot);
+    simulation.step_frames(3);
+    let safe_spot = simulation.position(player);
+
+    let pit = hazard_position(&mut simulation, Vec2::new(8., 3.));
+    simulation.teleport_player(pit);
+    simulation.step();
+    simulation.step_until("player back on safe ground", |simulation| {
+        simulation.position(player).distance(safe_spot) < GRID_SIZE / 2.
+    });
+}
+
+#[test]
+fn walking_along_the_edge_of_a_pit_does_not_fall_into_it() {
+    let mut simulation = Simulation::with_map(FIXTURE, hazards);
+    let player = simulation.player();
+
+    let safe_spot = hazard_position(&mut simulation, Vec2::new(5., 3.));
+    simulation.teleport_player(safe_spot);
+    simulation.step_frames(3);
+
+    // Left of the top half of the pit, the 16 pixels wide player overlaps it by 2 pixels
+    let pit = hazard_position(&mut simulation, Vec2::new(8., 3.));
+    let edge = pit + Vec2::new(-GRID_SIZE - 6., GRID_SIZE / 2.);
+    simulation.teleport_player(edge);
+    simulation.step();
+    for _ in 0..20 {
+        simulation.hold_movement(Vec2::Y, 1);
+        let position = simulation.position(player);
+        assert!(
+            (position.x - edge.x).abs() < 1.,
+            "player fell into the pit at {position}"
+        );
+    }
+    assert!(simulation.position(player).y < edge.y - 1.);
+}
+
+#[test]
+fn lava_sets_the_player_on_fire() {
+    let mut simulation = Simulation::with_map(FIXTURE, hazards);
+    let player = simulation.player();
+
+    let lava = hazard_position(&mut simulation, Vec2::new(2., 15.));
+    simulation.teleport_player(lava);
+    simulation.step_until("player burning", |simulation| {
+        simulation
+            .world()
+            .get::<StatusEffects>(player)
+            .unwrap()
+            .has(StatusKind::Burn)
+    });
+}
+
+#[test]
+fn mud_slows_the_player_down_while_in_it() {
+    let mut simulation = Simulation::with_map(FIXTURE, hazards);
+    let player = simulation.player();
+    let start = simulation.position(player);
+    let acceleration = |simulation: &mut Simulation| {
+        simulation
+            .world()
+            .get::<Stats>(player)
+            .unwrap()
+            .get(Stat::MoveAcceleration)
+    };
+
+    let mud = hazard_position(&mut simulation, Vec2::new(8., 15.));
+    simulation.teleport_player(mud);
+    simulation.step_until("player slowed by the mud", |simulation| {
+        acceleration(simulation) < PLAYER_ACCELERATION
+    });
+    assert!((acceleration(&mut simulation) - PLAYER_ACCELERATION * (1. - MUD_SLOW)).abs() < 1e-3);
+
+    simulation.teleport_player(start);
+    simulation.step_until("player out of the mud", |simulation| {
+        acceleration(simulation) == PLAYER_ACCELERATION
+    });
+}
+
+/// Room_0 without the mob and an empty copy of it to its east, Room_1
+fn two_rooms(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+    map.copy_level(ROOM, "Room_1", IVec2::new(ROOM_SIZE.x as i32, 0));
+}
+
+fn wait_for_room(simulation: &mut Simulation, identifier: &str) {
+    let level_iid = simulation.level_iid(identifier);
+    simulation.step_until(&format!("player in {identifier}"), |simulation| {
+        simulation.world().resource::<CurrentRoom>().level_iid() == Some(&level_iid)
+    });
+}
+
+fn run_state(simulation: &mut Simulation) -> RunState {
+    *simulation.world().resource::<State<RunState>>().get()
+}
+
+#[test]
+fn changing_room_sends_exit_and_enter_events() {
+    let mut simulation = Simulation::with_map(FIXTURE, two_rooms);
+    let room_0 = simulation.level_iid("Room_0");
+    let room_1 = simulation.level_iid("Room_1");
+    wait_for_room(&mut simulation, "Room_0");
+
+    // Only the events sent from now on are read
+    let mut exited_reader = simulation
+        .world()
+        .resource::<Events<RoomExited>>()
+        .get_reader_current();
+    let mut entered_reader = simulation
+        .world()
+        .resource::<Events<RoomEntered>>()
+        .get_reader_current();
+
+    let room_1_center = simulation.level_origin("Room_0") + ROOM_SIZE * Vec2::new(1.5, 0.5);
+    simulation.teleport_player(room_1_center);
+    wait_for_room(&mut simulation, "Room_1");
+
+    let world = simulation.world();
+    let exited: Vec<RoomExited> = exited_reader
+        .read(world.resource::<Events<RoomExited>>())
+        .cloned()
+        .collect();
+    let entered: Vec<RoomEntered> = entered_reader
+        .read(world.resource::<Events<RoomEntered>>())
+        .cloned()
+        .collect();
+    assert_eq!(exited, vec![RoomExited { level_iid: room_0 }]);
+    assert_eq!(
+        entered,
+        vec![RoomEntered {
+            level_iid: room_1.clone()
+        }]
+    );
+    assert!(matches!(
+        simulation.world().resource::<LevelSelection>(),
+        LevelSelection::Iid(selected) if *selected == room_1
+    ));
+
+    simulation.step_frames(10);
+    let world = simulation.world();
+    assert_eq!(
+        exited_reader
+            .read(world.resource::<Events<RoomExited>>())
+            .count(),
+        0
+    );
+    assert_eq!(
+        entered_reader
+            .read(world.resource::<Events<RoomEntered>>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn pan_transition_freezes_the_player_while_the_camera_moves_to_the_next_room() {
+    let mut simulation = Simulation::with_map(FIXTURE, two_rooms);
+    simulation
+        .world()
+        .resource_mut::<Settings>()
+        .room_transition = RoomTransitionMode::Pan;
+    let player = simulation.player();
+    wait_for_room(&mut simulation, "Room_0");
+    let camera = simulation.entities_with::<PlayerCamera>()[0];
+
+    let room_1_center = simulation.level_origin("Room_0") + ROOM_SIZE * Vec2::new(1.5, 0.5);
+    simulation.teleport_player(room_1_center);
+    simulation.step_until("room transition started", |simulation| {
+        run_state(simulation) == RunState::RoomTransition
+    });
+
+    let position = simulation.position(player);
+    simulation.hold_movement(Vec2::X, 10);
+    assert_eq!(simulation.position(player), position);
+    assert!(simulation.position(camera).distance(room_1_center) > GRID_SIZE);
+
+    simulation.step_until("room transition over", |simulation| {
+        run_state(simulation) == RunState::Playing
+    });
+    simulation.step();
+    assert!(simulation.position(camera).distance(room_1_center) < 1.);
+}
+
+/// Centers, relative to Room_0, and sizes of the colliders of the entities with `C`
+fn collider_rects<C: Component>(simulation: &mut Simulation) -> Vec<(Vec2, Vec2)> {
+    simulation.step_frames(2);
+    let origin = simulation.level_origin("Room_0");
+    let world = simulation.world();
+    let mut rects: Vec<(Vec2, Vec2)> = world
+        .query_filtered::<(&GlobalTransform, &Collider), With<C>>()
+        .iter(world)
+        .map(|(transform, collider)| {
+            let half_extents = collider.shape().as_cuboid().unwrap().half_extents;
+            (
+                transform.translation().truncate() - origin,
+                Vec2::new(half_extents.x, half_extents.y) * 2.,
+            )
+        })
+        .collect();
+    rects.sort_by(|(a, _), (b, _)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    rects
+}
+
+/// Room_0 without the mob, with its collision layer last and a hazards layer on a finer,
+/// shifted grid
+fn layers(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+
+    let definitions = &mut map.project.defs.layers;
+    let collision = definitions
+        .iter()
+        .position(|layer| layer.identifier == "Collision")
+        .unwrap();
+    let collision = definitions.remove(collision);
+    definitions.push(collision);
+    let hazards = definitions
+        .iter_mut()
+        .find(|layer| layer.identifier == "Hazards")
+        .unwrap();
+    hazards.grid_size = 8;
+    hazards.px_offset_x = 4;
+    hazards.px_offset_y = 4;
+
+    let instances = map.level_mut(ROOM).layer_instances.as_mut().unwrap();
+    let collision = instances
+        .iter()
+        .position(|layer| layer.identifier == "Collision")
+        .unwrap();
+    let collision = instances.remove(collision);
+    instances.push(collision);
+    let hazards = map.layer_mut(ROOM, "Hazards");
+    hazards.grid_size = 8;
+    hazards.px_offset_x = 4;
+    hazards.px_offset_y = 4;
+    hazards.px_total_offset_x = 4;
+    hazards.px_total_offset_y = 4;
+    hazards.c_wid = 70;
+    hazards.c_hei = 38;
+    hazards.int_grid_csv = vec![0; 70 * 38];
+    map.set_cells(ROOM, "Hazards", patch(IVec2::new(10, 10)), 1);
+}
+
+#[test]
+fn walls_do_not_depend_on_the_order_of_the_layers() {
+    let mut reference = Simulation::new(FIXTURE);
+    let walls = collider_rects::<Terrain>(&mut reference);
+    assert!(!walls.is_empty());
+
+    let mut simulation = Simulation::with_map(FIXTURE, layers);
+    assert_eq!(collider_rects::<Terrain>(&mut simulation), walls);
+}
+
+#[test]
+fn colliders_follow_the_grid_size_and_offset_of_their_layer() {
+    let mut simulation = Simulation::with_map(FIXTURE, layers);
+
+    // 2x2 cells of 8 pixels, 10 cells away from the top left corner of a layer shifted by 4 pixels
+    assert_eq!(
+        collider_rects::<Hazard>(&mut simulation),
+        vec![(Vec2::new(92., 212.), Vec2::new(16., 16.))]
+    );
+}
+
+/// Room_0 without the mob, with a diagonal wall of 4 slope cells in its bottom left corner
+fn slopes(map: &mut MapEditor) {
+    map.remove_entities(ROOM, "Mob");
+    // Bottom left slopes, walled below
+    map.set_cells(ROOM, "Collision", (1..5).map(|i| IVec2::new(i, 13 + i)), 3);
+    map.set_cells(
+        ROOM,
+        "Collision",
+        (1..4).flat_map(|x| (x + 14..18).map(move |y| IVec2::new(x, y))),
+        1,
+    );
+}
+
+#[test]
+fn diagonal_walls_are_merged_into_one_triangle() {
+    let mut simulation = Simulation::with_map(FIXTURE, slopes);
+    simulation.step_frames(2);
+    let origin = simulation.level_origin("Room_0");
+
+    let world = simulation.world();
+    let triangles: Vec<(Vec2, [Vec2; 3])> = world
+        .query_filtered::<(&GlobalTransform, &Collider), With<Terrain>>()
+        .iter(world)
+        .filter_map(|(transform, collider)| {
+            let triangle = collider.shape().as_triangle()?;
+            Some((
+                transform.translation().truncate() - origin,
+                [triangle.a, triangle.b, triangle.c].map(|point| Vec2::new(point.x, point.y)),
+            ))
+        })
+        .collect();
+
+    // The 4x4 cells above the bottom left walls, with the right angle in the corner
+    assert_eq!(triangles.len(), 1);
+    let (center, vertices) = triangles[0];
+    assert_eq!(center, Vec2::new(3., 3.) * GRID_SIZE);
+    assert!(vertices.contains(&Vec2::new(-2., -2.) * GRID_SIZE));
+}
+
+#[test]
+fn the_player_slides_along_diagonal_walls() {
+    let mut simulation = Simulation::with_map(FIXTURE, slopes);
+    let player = simulation.player();
+    let origin = simulation.level_origin("Room_0");
+
+    // Above the face of the diagonal wall, which goes through (16, 80) and (80, 16)
+    let start = Vec2::new(44., 80.);
+    simulation.teleport_player(origin + start);
+    simulation.step();
+    simulation.hold_movement(Vec2::Y, 60);
+
+    let position = simulation.position(player) - origin;
+    assert!(position.x > start.x + 1., "player stuck at {position}");
+    assert!(position.y < start.y - 1., "player stuck at {position}");
+    // The bottom left corner of the 16x20 player stays above the face, where x + y = 96
+    let corner = position - Vec2::new(8., 10.);
+    assert!(
+        corner.x + corner.y > 6. * GRID_SIZE - 1.,
+        "player went through the wall at {position}"
+    );
+}